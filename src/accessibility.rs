@@ -0,0 +1,147 @@
+use serde::Deserialize;
+
+use crate::matroska::MatroskaTrack;
+use crate::plan::{Flag, TrackEdit};
+
+/// Wanted accessibility flags of the tracks, unset flags are ignored
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Accessibility {
+    pub hearing_impaired: Option<bool>,
+    pub visual_impaired: Option<bool>,
+    pub text_descriptions: Option<bool>,
+}
+
+impl Accessibility {
+    /// Get the number of flags of the track that are not the wanted ones, the lowest is the best
+    pub fn rank(&self, track: &MatroskaTrack) -> usize {
+        [
+            (Flag::HearingImpaired, self.hearing_impaired),
+            (Flag::VisualImpaired, self.visual_impaired),
+            (Flag::TextDescriptions, self.text_descriptions),
+        ]
+        .into_iter()
+        .filter(|(flag, wanted)| wanted.is_some_and(|wanted| has_flag(track, *flag) != wanted))
+        .count()
+    }
+}
+
+/// Get the accessibility flags given by the name of the track (e.g. 'SDH' or 'Audio Description')
+pub fn guess_flags(track: &MatroskaTrack) -> Vec<Flag> {
+    let name = track.name.as_deref().unwrap_or_default().to_lowercase();
+    let words: Vec<&str> = name
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect();
+
+    let mut flags = vec![];
+    if track.is_subtitle()
+        && (words.contains(&"sdh") || words.contains(&"cc") || name.contains("hearing impaired"))
+    {
+        flags.push(Flag::HearingImpaired);
+    }
+    if track.is_subtitle() && name.contains("text description") {
+        flags.push(Flag::TextDescriptions);
+    }
+    if track.is_audio()
+        && (words.contains(&"ad")
+            || name.contains("audio description")
+            || name.contains("described video"))
+    {
+        flags.push(Flag::VisualImpaired);
+    }
+    flags
+}
+
+/// Does the track have the flag, set or given by its name
+pub fn has_flag(track: &MatroskaTrack, flag: Flag) -> bool {
+    flag.get(track) || guess_flags(track).contains(&flag)
+}
+
+/// Plan the accessibility flags given by the names of the tracks, the set flags are never cleared
+pub fn plan_accessibility<'a>(tracks: Vec<&'a MatroskaTrack>) -> Vec<TrackEdit<'a>> {
+    tracks
+        .into_iter()
+        .map(|track| TrackEdit {
+            flags: guess_flags(track)
+                .into_iter()
+                .filter(|flag| !flag.get(track))
+                .map(|flag| (flag, true))
+                .collect(),
+            ..TrackEdit::untouched(track)
+        })
+        .filter(|edit| !edit.flags.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matroska::MatroskaTrackType;
+
+    fn track(type_: MatroskaTrackType, name: &str) -> MatroskaTrack {
+        MatroskaTrack {
+            id: 0,
+            number: Some(1),
+            name: Some(name.to_string()),
+            type_,
+            language: "eng".to_string(),
+            language_ietf: "en".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_guess_flags() {
+        let subtitles = MatroskaTrackType::Subtitles;
+        assert_eq!(
+            guess_flags(&track(subtitles.clone(), "English (SDH)")),
+            vec![Flag::HearingImpaired]
+        );
+        assert_eq!(
+            guess_flags(&track(subtitles.clone(), "English CC")),
+            vec![Flag::HearingImpaired]
+        );
+        assert!(guess_flags(&track(subtitles, "Accented Full")).is_empty());
+        assert_eq!(
+            guess_flags(&track(MatroskaTrackType::Audio, "Audio Description")),
+            vec![Flag::VisualImpaired]
+        );
+    }
+
+    #[test]
+    fn test_rank() {
+        let subtitles = MatroskaTrackType::Subtitles;
+        let sdh = track(subtitles.clone(), "SDH");
+        let mut flagged = track(subtitles.clone(), "Full");
+        flagged.hearing_impaired = true;
+        let full = track(subtitles, "Full");
+
+        let accessibility = Accessibility {
+            hearing_impaired: Some(false),
+            ..Default::default()
+        };
+        assert_eq!(accessibility.rank(&full), 0);
+        assert_eq!(accessibility.rank(&sdh), 1);
+        assert_eq!(accessibility.rank(&flagged), 1);
+        assert_eq!(Accessibility::default().rank(&sdh), 0);
+    }
+
+    #[test]
+    fn test_plan_accessibility() {
+        let mut flagged = track(MatroskaTrackType::Subtitles, "SDH");
+        flagged.hearing_impaired = true;
+        let tracks = [
+            track(MatroskaTrackType::Subtitles, "Full"),
+            track(MatroskaTrackType::Subtitles, "Full SDH"),
+            flagged,
+        ];
+
+        let edits = plan_accessibility(tracks.iter().collect());
+        assert_eq!(edits.len(), 1);
+        assert_eq!(
+            edits[0].to_args().unwrap(),
+            " --edit track:1 --set flag-hearing-impaired=1"
+        );
+    }
+}
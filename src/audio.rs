@@ -0,0 +1,106 @@
+use serde::Deserialize;
+
+use crate::matroska::MatroskaTrack;
+
+/// Wanted channels and sampling frequency of the audio tracks, unset fields are ignored
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AudioQuality {
+    /// Prefer the tracks with at least these channels, else the most channels
+    pub min_channels: Option<u64>,
+    /// Prefer the tracks with at most these channels, else the fewest channels
+    pub max_channels: Option<u64>,
+    /// Prefer the tracks sampled at least at this frequency in Hz, else the highest frequency
+    pub min_sampling_frequency: Option<u64>,
+}
+
+impl AudioQuality {
+    /// Get the rank of the track, the lowest is the best and unknown values come last
+    pub fn rank(&self, track: &MatroskaTrack) -> (u64, u64) {
+        let channels = match (track.audio_channels, self.min_channels, self.max_channels) {
+            (_, None, None) => 0,
+            (None, _, _) => u64::MAX,
+            (Some(channels), Some(min), _) if channels < min => u64::MAX - channels,
+            (Some(channels), _, Some(max)) if channels > max => channels,
+            (Some(_), _, _) => 0,
+        };
+        let frequency = match (track.audio_sampling_frequency, self.min_sampling_frequency) {
+            (_, None) => 0,
+            (None, Some(_)) => u64::MAX,
+            (Some(frequency), Some(min)) if frequency < min => u64::MAX - frequency,
+            (Some(_), Some(_)) => 0,
+        };
+        (channels, frequency)
+    }
+}
+
+/// Get the name of the channel layout (e.g. '5.1' for 6 channels)
+pub fn channel_layout(channels: u64) -> String {
+    match channels {
+        1 => "mono".to_owned(),
+        2 => "stereo".to_owned(),
+        6 => "5.1".to_owned(),
+        8 => "7.1".to_owned(),
+        channels => format!("{channels}ch"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matroska::MatroskaTrackType;
+
+    fn track(channels: Option<u64>, frequency: Option<u64>) -> MatroskaTrack {
+        MatroskaTrack {
+            id: 0,
+            audio_channels: channels,
+            audio_sampling_frequency: frequency,
+            type_: MatroskaTrackType::Audio,
+            language: "jpn".to_string(),
+            language_ietf: "ja".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_rank_min_channels() {
+        let quality = AudioQuality {
+            min_channels: Some(6),
+            ..Default::default()
+        };
+        assert_eq!(
+            quality.rank(&track(Some(6), None)),
+            quality.rank(&track(Some(8), None))
+        );
+        assert!(quality.rank(&track(Some(8), None)) < quality.rank(&track(Some(2), None)));
+        assert!(quality.rank(&track(Some(4), None)) < quality.rank(&track(Some(2), None)));
+        assert!(quality.rank(&track(Some(2), None)) < quality.rank(&track(None, None)));
+    }
+
+    #[test]
+    fn test_rank_max_channels() {
+        let quality = AudioQuality {
+            max_channels: Some(2),
+            ..Default::default()
+        };
+        assert!(quality.rank(&track(Some(2), None)) < quality.rank(&track(Some(6), None)));
+        assert!(quality.rank(&track(Some(6), None)) < quality.rank(&track(Some(8), None)));
+    }
+
+    #[test]
+    fn test_rank_sampling_frequency() {
+        let quality = AudioQuality {
+            min_sampling_frequency: Some(96000),
+            ..Default::default()
+        };
+        assert!(quality.rank(&track(None, Some(96000))) < quality.rank(&track(None, Some(48000))));
+        assert_eq!(AudioQuality::default().rank(&track(None, None)), (0, 0));
+    }
+
+    #[test]
+    fn test_channel_layout() {
+        assert_eq!(channel_layout(2), "stereo");
+        assert_eq!(channel_layout(6), "5.1");
+        assert_eq!(channel_layout(3), "3ch");
+    }
+}
@@ -0,0 +1,440 @@
+use std::path::PathBuf;
+
+use clap::builder::PossibleValue;
+use clap::{Parser, ValueEnum};
+
+use crate::files::WalkOptions;
+use crate::plan::ForcedMode;
+use crate::preference::Preference;
+use crate::rule::Rule;
+use crate::undetermined::{validate_language, LanguageRule};
+use crate::video::VideoQuality;
+
+const EXIT_CODES: &str = "\
+Exit codes:
+  0  Every file has been edited
+  1  Unexpected error
+  2  Some files could not be edited
+  3  No file could be edited
+  4  No file or no common track to edit
+  5  mkvtoolnix is not installed
+  6  Invalid command line";
+
+/// Update your mkv default subtitle and audio tracks in bulk
+#[derive(Debug, Parser)]
+#[command(version, about, after_help = EXIT_CODES)]
+pub struct Cli {
+    /// Files and directories to process
+    #[arg(value_name = "PATH", default_value = ".")]
+    pub paths: Vec<PathBuf>,
+
+    /// Walk into the subdirectories
+    #[arg(short, long)]
+    pub recursive: bool,
+
+    /// Maximum depth of the walk ('1' only processes the content of the directories)
+    #[arg(long, value_name = "DEPTH", requires = "recursive")]
+    pub max_depth: Option<usize>,
+
+    /// Walk into the symlinked directories
+    #[arg(short = 'L', long, conflicts_with = "skip_symlinks")]
+    pub follow_symlinks: bool,
+
+    /// Ignore every symlink
+    #[arg(long)]
+    pub skip_symlinks: bool,
+
+    /// Language of the audio track to set as default (e.g. 'jpn' or 'ja'), 'original' chooses the track flagged as the original language
+    #[arg(long, value_name = "LANGUAGE")]
+    pub audio: Option<String>,
+
+    /// Name of the audio track to set as default, matched case-insensitively as a substring
+    #[arg(long, value_name = "NAME", requires = "audio")]
+    pub audio_name: Option<String>,
+
+    /// Language of the subtitle track to set as default (e.g. 'eng' or 'en'), 'none' disables every subtitle
+    #[arg(long, value_name = "LANGUAGE")]
+    pub subtitle: Option<String>,
+
+    /// Name of the subtitle track to set as default, matched case-insensitively as a substring
+    #[arg(long, value_name = "NAME", requires = "subtitle")]
+    pub subtitle_name: Option<String>,
+
+    /// Set the original flag on the audio and subtitle tracks in the language (e.g. '--tag-original=jpn') and clear it
+    /// on the others, without a language the 'original' language of the policy files is used
+    #[arg(long, value_name = "LANGUAGE", num_args = 0..=1, require_equals = true, value_parser = parse_language)]
+    pub tag_original: Option<Option<String>>,
+
+    /// Subtitle to set as default when the default audio is in a language (e.g. 'jpn=eng:Full' or 'eng=none'),
+    /// with an optional forced mode (e.g. 'eng=eng:Signs!set'), the first matching rule wins over the other subtitle choices
+    #[arg(long = "subtitle-rule", value_name = "AUDIO=SUBTITLE[:NAME][!FORCED]")]
+    pub subtitle_rules: Vec<Rule>,
+
+    /// Name of the video track to set as default
+    #[arg(long, value_name = "NAME")]
+    pub video_name: Option<String>,
+
+    /// Prefer the video track with the closest height (e.g. '1080')
+    #[arg(long, value_name = "HEIGHT")]
+    pub video_height: Option<u64>,
+
+    /// Prefer the video track with the codec (e.g. 'HEVC')
+    #[arg(long, value_name = "CODEC")]
+    pub video_codec: Option<String>,
+
+    /// What to do with the forced flag of the subtitles
+    #[arg(long, value_enum, value_name = "MODE")]
+    pub forced: Option<ForcedMode>,
+
+    /// Set the hearing-impaired, visual-impaired and text-descriptions flags from the track names (e.g. 'SDH')
+    #[arg(long)]
+    pub tag_accessibility: bool,
+
+    /// List the undetermined ('und') tracks of the files and prompt their language,
+    /// the tracks are tagged before the default tracks are chosen
+    #[arg(long)]
+    pub tag_language: bool,
+
+    /// Language of the undetermined tracks of a type (e.g. 'audio=eng' or 'subtitle:Signs=en'),
+    /// the first matching rule wins over the prompt
+    #[arg(long = "language-rule", value_name = "TYPE[:NAME]=LANGUAGE")]
+    pub language_rules: Vec<LanguageRule>,
+
+    /// Path of the config file (default: '$XDG_CONFIG_HOME/mkv_default_track/config.toml')
+    #[arg(long, value_name = "FILE")]
+    pub config: Option<PathBuf>,
+
+    /// Choose the tracks of each file with a profile of the config file
+    #[arg(long, value_name = "NAME", conflicts_with_all = ["audio", "subtitle", "video_name", "video_height", "video_codec"])]
+    pub profile: Option<String>,
+
+    /// Ignore the '.mkvdefault.toml' policy files of the directories
+    #[arg(long)]
+    pub no_policy: bool,
+
+    /// Rewrite the files with mkvmerge so the default tracks come first, for the players ignoring the default flags
+    /// (the file is remuxed next to the original, checked, then renamed over it)
+    #[arg(long)]
+    pub remux: bool,
+
+    /// Print the mkvpropedit commands without running them
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Format of the commands printed by '--dry-run'
+    #[arg(long, value_enum, default_value_t = PlanFormat::Shell, requires = "dry_run")]
+    pub plan_format: PlanFormat,
+
+    /// Format of the results printed at the end of the run
+    #[arg(long, value_enum, default_value_t = ReportFormat::Text)]
+    pub report: ReportFormat,
+
+    /// Write the results as a json document to a file
+    #[arg(long, value_name = "FILE")]
+    pub report_file: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum PlanFormat {
+    /// Shell-quoted command lines with the planned flags of each track
+    Shell,
+    /// One json document per file with the argv and the planned flags
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ReportFormat {
+    /// One line per file
+    Text,
+    /// A json document with the edited tracks and the result of each file
+    Json,
+}
+
+impl ValueEnum for ForcedMode {
+    fn value_variants<'a>() -> &'a [Self] {
+        &[ForcedMode::Keep, ForcedMode::Set, ForcedMode::Clear]
+    }
+
+    fn to_possible_value(&self) -> Option<PossibleValue> {
+        Some(match self {
+            ForcedMode::Keep => PossibleValue::new("keep").help("Leave the forced flags untouched"),
+            ForcedMode::Set => PossibleValue::new("set")
+                .help("Set the forced flag on the chosen subtitle and clear it on the others"),
+            ForcedMode::Clear => PossibleValue::new("clear")
+                .help("Clear the forced flag on every subtitle, chosen or not"),
+        })
+    }
+}
+
+impl Cli {
+    /// Should the tracks be chosen with the prompts, a run tagging the flags or the languages by rules only chooses
+    /// the given tracks, the languages are tagged before the prompts
+    pub fn is_interactive(&self) -> bool {
+        self.audio.is_none()
+            && self.subtitle.is_none()
+            && self.video_preferences().is_empty()
+            && self.profile.is_none()
+            && !self.tag_accessibility
+            && self.tag_original.is_none()
+            && (self.language_rules.is_empty() || self.tag_language)
+    }
+
+    /// Should the undetermined tracks be tagged, by the prompts or by the rules
+    pub fn tags_languages(&self) -> bool {
+        self.tag_language || !self.language_rules.is_empty()
+    }
+
+    /// Should the policy files be read, they apply when the tracks are not given on the command line
+    pub fn uses_policies(&self) -> bool {
+        self.audio.is_none()
+            && self.subtitle.is_none()
+            && self.video_preferences().is_empty()
+            && self.profile.is_none()
+            && !self.no_policy
+    }
+
+    /// Get the preferences of the audio track given on the command line, empty if none is given
+    pub fn audio_preferences(&self) -> Vec<Preference> {
+        language_preferences(self.audio.as_deref(), self.audio_name.as_deref())
+    }
+
+    /// Get the preferences of the subtitle track given on the command line, empty if none is given
+    pub fn subtitle_preferences(&self) -> Vec<Preference> {
+        language_preferences(self.subtitle.as_deref(), self.subtitle_name.as_deref())
+    }
+
+    /// Get the preference of the video track given on the command line, empty if none is given
+    pub fn video_preferences(&self) -> Vec<Preference> {
+        if self.video_name.is_none() && self.video_height.is_none() && self.video_codec.is_none() {
+            return vec![];
+        }
+        vec![Preference {
+            name: self.video_name.clone(),
+            codecs: self.video_codec.iter().cloned().collect(),
+            video: VideoQuality {
+                height: self.video_height,
+            },
+            ..Default::default()
+        }]
+    }
+
+    /// Get the options of the files discovery
+    pub fn walk_options(&self) -> WalkOptions {
+        WalkOptions {
+            recursive: self.recursive,
+            max_depth: self.max_depth,
+            follow_symlinks: self.follow_symlinks,
+            skip_symlinks: self.skip_symlinks,
+        }
+    }
+}
+
+/// Get the preferences of a language used as a range ('en' matches 'en-US') and of an optional name,
+/// a commentary only matches when its name is given
+fn language_preferences(language: Option<&str>, name: Option<&str>) -> Vec<Preference> {
+    let Some(language) = language else {
+        return vec![];
+    };
+    let preference = Preference {
+        language: language.to_owned(),
+        name: name.map(str::to_owned),
+        ..Default::default()
+    };
+    match name {
+        Some(_) => vec![
+            preference.clone(),
+            Preference {
+                commentary: true,
+                ..preference
+            },
+        ],
+        None => vec![preference],
+    }
+}
+
+/// Parse a language of the command line, refused if it can't be written to a track
+fn parse_language(input: &str) -> Result<String, String> {
+    validate_language(input)?;
+    Ok(input.to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_interactive() {
+        let cli = Cli::parse_from(["mkv_default_track"]);
+        assert!(cli.is_interactive());
+    }
+
+    #[test]
+    fn test_is_not_interactive() {
+        let cli = Cli::parse_from(["mkv_default_track", "--audio", "jpn"]);
+        assert!(!cli.is_interactive());
+        assert_eq!(cli.audio.as_deref(), Some("jpn"));
+        assert_eq!(cli.subtitle, None);
+    }
+
+    #[test]
+    fn test_profile_conflicts_with_audio() {
+        let cli =
+            Cli::try_parse_from(["mkv_default_track", "--profile", "anime", "--audio", "jpn"]);
+        assert!(cli.is_err());
+
+        let cli = Cli::parse_from(["mkv_default_track", "--profile", "anime"]);
+        assert!(!cli.is_interactive());
+    }
+
+    #[test]
+    fn test_forced() {
+        let cli = Cli::parse_from(["mkv_default_track", "--forced", "clear"]);
+        assert_eq!(cli.forced, Some(ForcedMode::Clear));
+        assert!(Cli::try_parse_from(["mkv_default_track", "--forced", "unset"]).is_err());
+    }
+
+    #[test]
+    fn test_audio_preferences() {
+        let cli = Cli::parse_from(["mkv_default_track", "--audio", "en", "--subtitle", "none"]);
+        let preferences = cli.audio_preferences();
+        assert_eq!(preferences.len(), 1);
+        assert_eq!(preferences[0].language, "en");
+        assert!(!preferences[0].commentary);
+        assert!(cli.subtitle_preferences()[0].is_disabled());
+
+        let cli = Cli::parse_from([
+            "mkv_default_track",
+            "--audio",
+            "eng",
+            "--audio-name",
+            "Director",
+        ]);
+        let preferences = cli.audio_preferences();
+        assert_eq!(preferences.len(), 2);
+        assert_eq!(preferences[1].name.as_deref(), Some("Director"));
+        assert!(preferences[1].commentary);
+        assert!(cli.subtitle_preferences().is_empty());
+    }
+
+    #[test]
+    fn test_tag_accessibility_is_not_interactive() {
+        let cli = Cli::parse_from(["mkv_default_track", "--tag-accessibility"]);
+        assert!(!cli.is_interactive());
+    }
+
+    #[test]
+    fn test_tag_original() {
+        let cli = Cli::parse_from(["mkv_default_track", "--tag-original"]);
+        assert_eq!(cli.tag_original, Some(None));
+        assert!(!cli.is_interactive());
+        assert!(cli.uses_policies());
+
+        let cli = Cli::parse_from(["mkv_default_track", "--tag-original=jpn", "."]);
+        assert_eq!(cli.tag_original, Some(Some("jpn".to_string())));
+
+        let cli = Cli::parse_from(["mkv_default_track", "--tag-original", "Library/Show"]);
+        assert_eq!(cli.tag_original, Some(None));
+        assert_eq!(cli.paths, vec![PathBuf::from("Library/Show")]);
+
+        assert!(Cli::try_parse_from(["mkv_default_track", "--tag-original=Library/Show"]).is_err());
+        assert!(Cli::try_parse_from(["mkv_default_track", "--tag-original=und"]).is_err());
+    }
+
+    #[test]
+    fn test_language_rules() {
+        let cli = Cli::parse_from(["mkv_default_track", "--tag-language"]);
+        assert!(cli.tags_languages());
+        assert!(cli.is_interactive());
+
+        let cli = Cli::parse_from([
+            "mkv_default_track",
+            "--language-rule",
+            "audio=eng",
+            "--audio",
+            "eng",
+        ]);
+        assert!(cli.tags_languages());
+        assert!(!cli.is_interactive());
+        assert_eq!(cli.language_rules[0].language, "eng");
+
+        let cli = Cli::parse_from(["mkv_default_track", "--language-rule", "audio=eng"]);
+        assert!(!cli.is_interactive());
+
+        let cli = Cli::parse_from([
+            "mkv_default_track",
+            "--language-rule",
+            "audio=eng",
+            "--tag-language",
+        ]);
+        assert!(cli.is_interactive());
+
+        let cli = Cli::try_parse_from(["mkv_default_track", "--language-rule", "audio=und"]);
+        assert!(cli.is_err());
+    }
+
+    #[test]
+    fn test_subtitle_rules() {
+        let cli = Cli::parse_from([
+            "mkv_default_track",
+            "--subtitle-rule",
+            "jpn=eng:Full",
+            "--subtitle-rule",
+            "eng=none",
+        ]);
+        assert_eq!(cli.subtitle_rules.len(), 2);
+        assert_eq!(cli.subtitle_rules[1].audio, "eng");
+
+        let cli = Cli::try_parse_from(["mkv_default_track", "--subtitle-rule", "jpn"]);
+        assert!(cli.is_err());
+    }
+
+    #[test]
+    fn test_video_preferences() {
+        let cli = Cli::parse_from(["mkv_default_track"]);
+        assert!(cli.video_preferences().is_empty());
+
+        let cli = Cli::parse_from(["mkv_default_track", "--video-height", "1080"]);
+        assert!(!cli.is_interactive());
+        let preferences = cli.video_preferences();
+        assert_eq!(preferences[0].video.height, Some(1080));
+        assert_eq!(preferences[0].language, "");
+    }
+
+    #[test]
+    fn test_remux() {
+        let cli = Cli::parse_from(["mkv_default_track"]);
+        assert!(!cli.remux);
+
+        let cli = Cli::parse_from(["mkv_default_track", "--remux", "--audio", "jpn"]);
+        assert!(cli.remux);
+        assert!(!cli.is_interactive());
+    }
+
+    #[test]
+    fn test_paths() {
+        let cli = Cli::parse_from(["mkv_default_track"]);
+        assert_eq!(cli.paths, vec![PathBuf::from(".")]);
+
+        let cli = Cli::parse_from(["mkv_default_track", "-r", "Season 1", "Season 2"]);
+        assert_eq!(
+            cli.paths,
+            vec![PathBuf::from("Season 1"), PathBuf::from("Season 2")]
+        );
+        assert!(cli.walk_options().recursive);
+    }
+
+    #[test]
+    fn test_plan_format_requires_dry_run() {
+        let cli = Cli::try_parse_from(["mkv_default_track", "--plan-format", "json"]);
+        assert!(cli.is_err());
+
+        let cli = Cli::parse_from(["mkv_default_track", "--dry-run", "--plan-format", "json"]);
+        assert_eq!(cli.plan_format, PlanFormat::Json);
+    }
+
+    #[test]
+    fn test_name_requires_language() {
+        let cli = Cli::try_parse_from(["mkv_default_track", "--subtitle-name", "Full"]);
+        assert!(cli.is_err());
+    }
+}
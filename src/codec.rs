@@ -0,0 +1,98 @@
+use crate::matroska::MatroskaTrack;
+
+/// Class of the text subtitles (SRT, ASS, WebVTT...)
+pub const TEXT_SUBTITLES: &str = "text";
+
+/// Class of the image subtitles (PGS, VobSub, DVB)
+pub const IMAGE_SUBTITLES: &str = "image";
+
+/// Codec ids of the image subtitles, 'S_IMAGE/' is a prefix
+const IMAGE_CODEC_IDS: &[&str] = &["S_HDMV/PGS", "S_VOBSUB", "S_DVBSUB", "S_IMAGE/"];
+
+/// Does the codec of the track match the pattern: a codec id with or without its
+/// type prefix ('A_AC3' or 'AC3'), a codec name or one of its aliases ('AC-3' or 'H.264') or a class of subtitles ('text' or 'image')
+pub fn matches(track: &MatroskaTrack, pattern: &str) -> bool {
+    let codec_id = track.codec_id.as_deref().unwrap_or_default().to_uppercase();
+    let pattern = pattern.to_uppercase();
+
+    if pattern.eq_ignore_ascii_case(TEXT_SUBTITLES) {
+        return codec_id.starts_with("S_TEXT/");
+    }
+    if pattern.eq_ignore_ascii_case(IMAGE_SUBTITLES) {
+        return IMAGE_CODEC_IDS.iter().any(|id| codec_id.starts_with(id));
+    }
+
+    // 'A_AAC/MPEG4/LC' is 'AAC'
+    let short_id = codec_id
+        .split_once('_')
+        .map_or(codec_id.as_str(), |(_, id)| id);
+    let is_id = |id: &str| id == pattern || id.starts_with(&format!("{pattern}/"));
+
+    // 'HEVC/H.265/MPEG-H' is 'HEVC' or 'H.265'
+    let is_name = |codec: &str| {
+        let codec = codec.to_uppercase();
+        codec == pattern || codec.split('/').any(|part| part == pattern)
+    };
+
+    is_id(&codec_id) || is_id(short_id) || track.codec.as_deref().is_some_and(is_name)
+}
+
+/// Get the position of the codec of the track in the ranking, unranked codecs come last
+pub fn rank(track: &MatroskaTrack, ranking: &[String]) -> usize {
+    ranking
+        .iter()
+        .position(|pattern| matches(track, pattern))
+        .unwrap_or(ranking.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matroska::MatroskaTrackType;
+
+    fn track(codec: &str, codec_id: &str) -> MatroskaTrack {
+        MatroskaTrack {
+            id: 0,
+            codec: Some(codec.to_string()),
+            codec_id: Some(codec_id.to_string()),
+            type_: MatroskaTrackType::Audio,
+            language: "eng".to_string(),
+            language_ietf: "en".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_matches() {
+        assert!(matches(&track("FLAC", "A_FLAC"), "flac"));
+        assert!(matches(&track("AAC", "A_AAC/MPEG4/LC"), "AAC"));
+        assert!(matches(&track("AC-3", "A_AC3"), "AC3"));
+        assert!(matches(&track("AC-3", "A_AC3"), "AC-3"));
+        assert!(!matches(&track("E-AC-3", "A_EAC3"), "AC3"));
+        assert!(matches(&track("TrueHD Atmos", "A_TRUEHD"), "TrueHD"));
+        assert!(matches(
+            &track("HEVC/H.265/MPEG-H", "V_MPEGH/ISO/HEVC"),
+            "hevc"
+        ));
+        assert!(matches(
+            &track("AVC/H.264/MPEG-4p10", "V_MPEG4/ISO/AVC"),
+            "H.264"
+        ));
+    }
+
+    #[test]
+    fn test_matches_subtitle_class() {
+        assert!(matches(&track("SubStationAlpha", "S_TEXT/ASS"), "text"));
+        assert!(!matches(&track("SubStationAlpha", "S_TEXT/ASS"), "image"));
+        assert!(matches(&track("HDMV PGS", "S_HDMV/PGS"), "image"));
+        assert!(matches(&track("VobSub", "S_VOBSUB"), "image"));
+    }
+
+    #[test]
+    fn test_rank() {
+        let ranking = ["FLAC", "TrueHD", "AC3", "AAC"].map(String::from);
+        assert_eq!(rank(&track("FLAC", "A_FLAC"), &ranking), 0);
+        assert_eq!(rank(&track("AAC", "A_AAC"), &ranking), 3);
+        assert_eq!(rank(&track("Opus", "A_OPUS"), &ranking), 4);
+    }
+}
@@ -0,0 +1,156 @@
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::error::{TempError, TempResult};
+use crate::plan::ForcedMode;
+use crate::preference::Preference;
+use crate::rule::Rule;
+
+/// Content of the config file
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Profile used when no track is given on the command line
+    pub default_profile: Option<String>,
+    #[serde(default)]
+    pub profiles: HashMap<String, Profile>,
+}
+
+/// Ordered preferences of the audio, subtitle and video tracks
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    #[serde(default)]
+    pub audio: Vec<Preference>,
+    #[serde(default)]
+    pub subtitle: Vec<Preference>,
+    #[serde(default)]
+    pub video: Vec<Preference>,
+    /// What to do with the forced flag of the subtitles
+    pub forced: Option<ForcedMode>,
+    /// Subtitle preferences chosen by the default audio of each file, the first matching rule wins
+    #[serde(default)]
+    pub rules: Vec<Rule>,
+}
+
+impl Config {
+    /// Create a Config from a string
+    pub fn from_string(path: &Path, input: &str) -> TempResult<Self> {
+        toml::from_str(input).map_err(|err| TempError::Config(path.to_owned(), err))
+    }
+
+    /// Load the config from 'path', or from the default path if it exists
+    pub fn load(path: Option<&Path>) -> TempResult<Self> {
+        let (path, required) = match path {
+            Some(path) => (path.to_owned(), true),
+            None => match default_config_path() {
+                Some(path) => (path, false),
+                None => return Ok(Self::default()),
+            },
+        };
+
+        match fs::read_to_string(&path) {
+            Ok(input) => Self::from_string(&path, &input),
+            Err(err) if !required && err.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(TempError::Io(path, err)),
+        }
+    }
+
+    /// Get a profile by its name
+    pub fn profile(&self, name: &str) -> TempResult<&Profile> {
+        self.profiles
+            .get(name)
+            .ok_or_else(|| TempError::ProfileNotFound(name.to_owned()))
+    }
+}
+
+/// Get the default path of the config file ('$XDG_CONFIG_HOME/mkv_default_track/config.toml')
+pub fn default_config_path() -> Option<PathBuf> {
+    let config_home = match env::var_os("XDG_CONFIG_HOME") {
+        Some(path) if !path.is_empty() => PathBuf::from(path),
+        _ => PathBuf::from(env::var_os("HOME")?).join(".config"),
+    };
+    Some(config_home.join("mkv_default_track").join("config.toml"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_from_string() {
+        let input = r#"
+            default_profile = "anime"
+
+            [profiles.anime]
+            audio = [{ language = "jpn" }, { language = "eng" }]
+            subtitle = [
+                { language = "eng", name = "Full" },
+                { language = "eng", flags = { default = true } },
+            ]
+            forced = "clear"
+        "#;
+        let config = Config::from_string(Path::new("config.toml"), input).unwrap();
+        assert_eq!(config.default_profile.as_deref(), Some("anime"));
+
+        let profile = config.profile("anime").unwrap();
+        assert_eq!(profile.audio.len(), 2);
+        assert_eq!(profile.audio[1].language, "eng");
+        assert_eq!(profile.subtitle[0].name.as_deref(), Some("Full"));
+        assert_eq!(profile.subtitle[1].flags.default, Some(true));
+        assert_eq!(profile.forced, Some(ForcedMode::Clear));
+    }
+
+    #[test]
+    fn test_config_rules() {
+        let input = r#"
+            [profiles.anime]
+            audio = [{ language = "jpn" }, { language = "eng" }]
+
+            [[profiles.anime.rules]]
+            audio = "jpn"
+            subtitle = [{ language = "eng", name = "Full" }]
+
+            [[profiles.anime.rules]]
+            audio = "eng"
+            subtitle = [{ language = "eng", name = "Signs & Songs" }]
+            forced = "set"
+        "#;
+        let config = Config::from_string(Path::new("config.toml"), input).unwrap();
+        let profile = config.profile("anime").unwrap();
+        assert_eq!(profile.rules.len(), 2);
+        assert_eq!(profile.rules[0].audio, "jpn");
+        assert_eq!(profile.rules[1].forced, Some(ForcedMode::Set));
+    }
+
+    #[test]
+    fn test_config_unknown_profile() {
+        let config = Config::default();
+        assert!(matches!(
+            config.profile("anime"),
+            Err(TempError::ProfileNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_config_invalid() {
+        let input = r#"
+            [profiles.anime]
+            audio = [{ lang = "jpn" }]
+        "#;
+        let result = Config::from_string(Path::new("config.toml"), input);
+        assert!(matches!(result, Err(TempError::Config(_, _))));
+    }
+
+    #[test]
+    fn test_config_load_missing() {
+        let dir = tempfile::tempdir().unwrap();
+        let result = Config::load(Some(&dir.path().join("config.toml")));
+        assert!(matches!(result, Err(TempError::Io(_, _))));
+    }
+}
@@ -2,29 +2,74 @@ use serde::Deserialize;
 
 #[derive(Debug, Deserialize)]
 pub struct DeserializeMatroska {
+    pub container: Option<DeserializeMatroskaContainer>,
+    #[serde(default)]
     pub tracks: Vec<DeserializeMatroskaTrack>,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct DeserializeMatroskaContainer {
+    pub recognized: bool,
+}
+
 #[derive(Debug, Deserialize, Clone)]
 pub struct DeserializeMatroskaTrack {
     pub id: usize,
     #[serde(rename = "type")]
     pub type_: DeserializeMatroskaTrackType,
+    pub codec: Option<String>,
     pub properties: DeserializeMatroskaTrackProperties,
 }
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct DeserializeMatroskaTrackProperties {
+    pub number: Option<u64>,
+    pub uid: Option<u64>,
+    pub codec_id: Option<String>,
+    pub audio_channels: Option<u64>,
+    pub audio_sampling_frequency: Option<u64>,
+    pub audio_bits_per_sample: Option<u64>,
+    pub video_pixel_dimensions: Option<String>,
     pub default_track: bool,
+    #[serde(default)]
+    pub forced_track: bool,
+    #[serde(default)]
+    pub flag_commentary: bool,
+    #[serde(default)]
+    pub flag_hearing_impaired: bool,
+    #[serde(default)]
+    pub flag_visual_impaired: bool,
+    #[serde(default)]
+    pub flag_text_descriptions: bool,
+    #[serde(default)]
+    pub flag_original: bool,
     pub track_name: Option<String>,
+    #[serde(default = "default_language")]
     pub language: String,
     pub language_ietf: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Clone)]
-#[serde(rename_all = "snake_case")]
+#[serde(from = "String")]
 pub enum DeserializeMatroskaTrackType {
     Audio,
     Video,
     Subtitles,
+    Other(String),
+}
+
+impl From<String> for DeserializeMatroskaTrackType {
+    fn from(type_: String) -> Self {
+        match type_.as_str() {
+            "audio" => DeserializeMatroskaTrackType::Audio,
+            "video" => DeserializeMatroskaTrackType::Video,
+            "subtitles" => DeserializeMatroskaTrackType::Subtitles,
+            _ => DeserializeMatroskaTrackType::Other(type_),
+        }
+    }
+}
+
+/// Tracks without a language (e.g. buttons) are undetermined
+fn default_language() -> String {
+    "und".to_string()
 }
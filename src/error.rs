@@ -1,5 +1,12 @@
+use std::io;
+use std::path::PathBuf;
+use std::process::ExitStatus;
+
 use thiserror::Error;
 
+use crate::matroska::MatroskaTrackType;
+use crate::outcome::Outcome;
+
 #[derive(Error, Debug)]
 pub enum TempError {
     #[error("Unable to deserialize")]
@@ -7,6 +14,117 @@ pub enum TempError {
 
     #[error("Unable to serialize")]
     Serialize(#[source] serde_json::Error),
+
+    #[error("Unable to access '{}'", .0.display())]
+    Io(PathBuf, #[source] io::Error),
+
+    #[error("Unable to find '{0}', is mkvtoolnix installed?")]
+    ToolNotFound(String),
+
+    #[error("'{0}' failed ({1}): {2}")]
+    ToolFailed(String, ExitStatus, String),
+
+    #[error("Unable to prompt, no terminal is attached")]
+    NoTerminal(#[source] io::Error),
+
+    #[error("Unable to recognize '{}' as a Matroska file", .0.display())]
+    UnrecognizedFile(PathBuf),
+
+    #[error("Unable to parse the config '{}'", .0.display())]
+    Config(PathBuf, #[source] toml::de::Error),
+
+    #[error("Unable to find the profile '{0}'")]
+    ProfileNotFound(String),
+
+    #[error("Unable to find a common {0} track matching '{1}'")]
+    TrackNotFound(MatroskaTrackType, String),
+
+    #[error("Unable to select the track {0} with mkvpropedit, it has neither a uid nor a number")]
+    UnselectableTrack(usize),
+
+    #[error("Not enough free space to remux '{}': {1} bytes needed, {2} available", .0.display())]
+    NotEnoughSpace(PathBuf, u64, u64),
+
+    #[error("The remuxed file of '{}' does not have the planned tracks, the original is kept", .0.display())]
+    RemuxMismatch(PathBuf),
+}
+
+impl TempError {
+    /// Get the outcome of the run for this error
+    pub fn outcome(&self) -> Outcome {
+        match self {
+            TempError::ToolNotFound(_) => Outcome::Environment,
+            TempError::TrackNotFound(_, _) => Outcome::NothingToDo,
+            _ => Outcome::Error,
+        }
+    }
+
+    /// Get a hint on how to fix the error
+    pub fn hint(&self) -> Option<&'static str> {
+        match self {
+            TempError::Deserialize(_) => {
+                Some("Make sure mkvtoolnix is up to date, the output of 'mkvmerge -J' is unexpected")
+            }
+            TempError::Serialize(_) => None,
+            TempError::Io(_, _) => {
+                Some("Make sure the path exists and that you have the permission to access it")
+            }
+            TempError::ToolNotFound(_) => Some(
+                "Install mkvtoolnix (https://mkvtoolnix.download) and make sure 'mkvmerge' and 'mkvpropedit' are in your PATH",
+            ),
+            TempError::ToolFailed(_, _, _) => {
+                Some("Run the command manually to check that the file is not corrupted")
+            }
+            TempError::NoTerminal(_) => Some(
+                "Run the command in a terminal, or choose the tracks with '--audio' and '--subtitle'",
+            ),
+            TempError::UnrecognizedFile(_) => {
+                Some("Make sure the file is a valid Matroska file or remove it from the paths")
+            }
+            TempError::Config(_, _) => {
+                Some("Fix the config file, see the README for the expected format")
+            }
+            TempError::ProfileNotFound(_) => {
+                Some("Add the profile to the '[profiles]' table of the config file")
+            }
+            TempError::TrackNotFound(_, _) => {
+                Some("Run the command without '--audio' and '--subtitle' to list the common tracks")
+            }
+            TempError::UnselectableTrack(_) => {
+                Some("Run with '--remux' to rewrite the file with mkvmerge, it selects the tracks by id")
+            }
+            TempError::NotEnoughSpace(_, _, _) => {
+                Some("Free some space, or run without '--remux' to edit the flags in place")
+            }
+            TempError::RemuxMismatch(_) => Some(
+                "Run the mkvmerge command printed by '--dry-run --remux' manually to check its output",
+            ),
+        }
+    }
 }
 
 pub type TempResult<T = ()> = Result<T, TempError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_outcome() {
+        let err = TempError::ToolNotFound("mkvmerge".to_string());
+        assert_eq!(err.outcome(), Outcome::Environment);
+
+        let err = TempError::UnrecognizedFile(PathBuf::from("a.mkv"));
+        assert_eq!(err.outcome(), Outcome::Error);
+    }
+
+    #[test]
+    fn test_display() {
+        let err = TempError::UnrecognizedFile(PathBuf::from("./a.mkv"));
+        assert_eq!(
+            err.to_string(),
+            "Unable to recognize './a.mkv' as a Matroska file"
+        );
+        assert!(err.hint().is_some());
+    }
+}
@@ -0,0 +1,223 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::{fs, io};
+
+use walkdir::WalkDir;
+
+use crate::error::{TempError, TempResult};
+
+/// How the directories are walked
+#[derive(Debug, Default, Clone)]
+pub struct WalkOptions {
+    pub recursive: bool,
+    pub max_depth: Option<usize>,
+    pub follow_symlinks: bool,
+    pub skip_symlinks: bool,
+}
+
+impl WalkOptions {
+    /// Get the maximum depth of the walk ('1' means the content of the directory)
+    fn depth(&self) -> usize {
+        match (self.recursive, self.max_depth) {
+            (false, _) => 1,
+            (true, Some(depth)) => depth.max(1),
+            (true, None) => usize::MAX,
+        }
+    }
+}
+
+/// Get the files from the 'paths' (files or directories) without duplicates
+pub fn get_files(paths: &[PathBuf], options: &WalkOptions) -> TempResult<Vec<PathBuf>> {
+    let mut seen: HashSet<PathBuf> = HashSet::new();
+    let mut files: Vec<PathBuf> = vec![];
+
+    for path in paths {
+        for file in get_files_from_path(path, options)? {
+            let key = fs::canonicalize(&file).unwrap_or_else(|_| file.clone());
+            if seen.insert(key) {
+                files.push(file);
+            }
+        }
+    }
+    Ok(files)
+}
+
+/// Get the files from a single path, sorted by path
+fn get_files_from_path(path: &Path, options: &WalkOptions) -> TempResult<Vec<PathBuf>> {
+    let mut files: Vec<PathBuf> = vec![];
+    let walk = WalkDir::new(path)
+        .max_depth(options.depth())
+        .follow_links(options.follow_symlinks)
+        .sort_by_file_name();
+
+    for entry in walk {
+        let entry = match entry {
+            Ok(entry) => entry,
+            // A symlink pointing to one of its parents, it has already been walked
+            Err(err) if err.loop_ancestor().is_some() => continue,
+            // An unreadable entry under the path is skipped, the other files are still walked
+            Err(err) if err.depth() > 0 => {
+                eprintln!("Warning: {err}, skipped");
+                continue;
+            }
+            Err(err) => {
+                let path = err.path().unwrap_or(path).to_owned();
+                let err = err
+                    .into_io_error()
+                    .unwrap_or_else(|| io::Error::other("unable to walk the directory"));
+                return Err(TempError::Io(path, err));
+            }
+        };
+
+        if options.skip_symlinks && entry.path_is_symlink() {
+            continue;
+        }
+        if entry.file_type().is_file() || (entry.path_is_symlink() && entry.path().is_file()) {
+            files.push(entry.into_path());
+        }
+    }
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn touch(path: &Path) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, b"").unwrap();
+    }
+
+    #[test]
+    fn test_get_files_not_recursive() {
+        let dir = tempfile::tempdir().unwrap();
+        touch(&dir.path().join("b.mkv"));
+        touch(&dir.path().join("a.mkv"));
+        touch(&dir.path().join("season 1/c.mkv"));
+
+        let files = get_files(&[dir.path().to_path_buf()], &WalkOptions::default()).unwrap();
+        assert_eq!(
+            files,
+            vec![dir.path().join("a.mkv"), dir.path().join("b.mkv")]
+        );
+    }
+
+    #[test]
+    fn test_get_files_recursive_max_depth() {
+        let dir = tempfile::tempdir().unwrap();
+        touch(&dir.path().join("a.mkv"));
+        touch(&dir.path().join("season 1/b.mkv"));
+        touch(&dir.path().join("season 1/extras/c.mkv"));
+
+        let options = WalkOptions {
+            recursive: true,
+            max_depth: Some(2),
+            ..Default::default()
+        };
+        let files = get_files(&[dir.path().to_path_buf()], &options).unwrap();
+        assert_eq!(
+            files,
+            vec![dir.path().join("a.mkv"), dir.path().join("season 1/b.mkv")]
+        );
+
+        let options = WalkOptions {
+            recursive: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            get_files(&[dir.path().to_path_buf()], &options)
+                .unwrap()
+                .len(),
+            3
+        );
+    }
+
+    #[test]
+    fn test_get_files_duplicates() {
+        let dir = tempfile::tempdir().unwrap();
+        touch(&dir.path().join("a.mkv"));
+
+        let paths = [dir.path().join("a.mkv"), dir.path().to_path_buf()];
+        let files = get_files(&paths, &WalkOptions::default()).unwrap();
+        assert_eq!(files, vec![dir.path().join("a.mkv")]);
+    }
+
+    #[test]
+    fn test_get_files_missing_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let paths = [dir.path().join("missing")];
+        let result = get_files(&paths, &WalkOptions::default());
+        assert!(matches!(result, Err(TempError::Io(path, _)) if path == paths[0]));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_get_files_unreadable_directory() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        touch(&dir.path().join("a.mkv"));
+        touch(&dir.path().join("locked/b.mkv"));
+        let locked = dir.path().join("locked");
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o000)).unwrap();
+        // The permissions don't apply to root, the directory is readable anyway
+        let readable = fs::read_dir(&locked).is_ok();
+
+        let options = WalkOptions {
+            recursive: true,
+            ..Default::default()
+        };
+        let files = get_files(&[dir.path().to_path_buf()], &options);
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o755)).unwrap();
+
+        let files = files.unwrap();
+        assert_eq!(files[0], dir.path().join("a.mkv"));
+        assert_eq!(files.len(), if readable { 2 } else { 1 });
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_get_files_symlinks() {
+        let dir = tempfile::tempdir().unwrap();
+        touch(&dir.path().join("show/a.mkv"));
+        touch(&dir.path().join("show/c.mkv"));
+        fs::create_dir(dir.path().join("library")).unwrap();
+        std::os::unix::fs::symlink(dir.path().join("show"), dir.path().join("library/show"))
+            .unwrap();
+        std::os::unix::fs::symlink(
+            dir.path().join("show/a.mkv"),
+            dir.path().join("library/b.mkv"),
+        )
+        .unwrap();
+
+        let root = [dir.path().join("library")];
+        let options = WalkOptions {
+            recursive: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            get_files(&root, &options).unwrap(),
+            vec![dir.path().join("library/b.mkv")]
+        );
+
+        let options = WalkOptions {
+            recursive: true,
+            follow_symlinks: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            get_files(&root, &options).unwrap(),
+            vec![
+                dir.path().join("library/b.mkv"),
+                dir.path().join("library/show/c.mkv")
+            ]
+        );
+
+        let options = WalkOptions {
+            recursive: true,
+            skip_symlinks: true,
+            ..Default::default()
+        };
+        assert!(get_files(&root, &options).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,326 @@
+use std::fmt;
+
+/// ISO 639-2/B, ISO 639-2/T and ISO 639-1 codes of the languages ('' when there is no ISO 639-1 code)
+const LANGUAGES: &[(&str, &str, &str)] = &[
+    ("afr", "afr", "af"),
+    ("alb", "sqi", "sq"),
+    ("amh", "amh", "am"),
+    ("ara", "ara", "ar"),
+    ("arm", "hye", "hy"),
+    ("aze", "aze", "az"),
+    ("baq", "eus", "eu"),
+    ("bel", "bel", "be"),
+    ("ben", "ben", "bn"),
+    ("bos", "bos", "bs"),
+    ("bul", "bul", "bg"),
+    ("bur", "mya", "my"),
+    ("cat", "cat", "ca"),
+    ("chi", "zho", "zh"),
+    ("cze", "ces", "cs"),
+    ("dan", "dan", "da"),
+    ("dut", "nld", "nl"),
+    ("eng", "eng", "en"),
+    ("epo", "epo", "eo"),
+    ("est", "est", "et"),
+    ("fil", "fil", ""),
+    ("fin", "fin", "fi"),
+    ("fre", "fra", "fr"),
+    ("geo", "kat", "ka"),
+    ("ger", "deu", "de"),
+    ("gle", "gle", "ga"),
+    ("glg", "glg", "gl"),
+    ("gre", "ell", "el"),
+    ("guj", "guj", "gu"),
+    ("heb", "heb", "he"),
+    ("hin", "hin", "hi"),
+    ("hrv", "hrv", "hr"),
+    ("hun", "hun", "hu"),
+    ("ice", "isl", "is"),
+    ("ind", "ind", "id"),
+    ("ita", "ita", "it"),
+    ("jpn", "jpn", "ja"),
+    ("kan", "kan", "kn"),
+    ("kaz", "kaz", "kk"),
+    ("khm", "khm", "km"),
+    ("kor", "kor", "ko"),
+    ("lao", "lao", "lo"),
+    ("lat", "lat", "la"),
+    ("lav", "lav", "lv"),
+    ("lit", "lit", "lt"),
+    ("mac", "mkd", "mk"),
+    ("mal", "mal", "ml"),
+    ("mao", "mri", "mi"),
+    ("mar", "mar", "mr"),
+    ("may", "msa", "ms"),
+    ("mon", "mon", "mn"),
+    ("nep", "nep", "ne"),
+    ("nno", "nno", "nn"),
+    ("nob", "nob", "nb"),
+    ("nor", "nor", "no"),
+    ("pan", "pan", "pa"),
+    ("per", "fas", "fa"),
+    ("pol", "pol", "pl"),
+    ("por", "por", "pt"),
+    ("rum", "ron", "ro"),
+    ("rus", "rus", "ru"),
+    ("sin", "sin", "si"),
+    ("slo", "slk", "sk"),
+    ("slv", "slv", "sl"),
+    ("spa", "spa", "es"),
+    ("srp", "srp", "sr"),
+    ("swa", "swa", "sw"),
+    ("swe", "swe", "sv"),
+    ("tam", "tam", "ta"),
+    ("tel", "tel", "te"),
+    ("tgl", "tgl", "tl"),
+    ("tha", "tha", "th"),
+    ("tib", "bod", "bo"),
+    ("tur", "tur", "tr"),
+    ("ukr", "ukr", "uk"),
+    ("urd", "urd", "ur"),
+    ("uzb", "uzb", "uz"),
+    ("vie", "vie", "vi"),
+    ("wel", "cym", "cy"),
+    ("yid", "yid", "yi"),
+    ("yue", "yue", ""),
+    ("zul", "zul", "zu"),
+];
+
+/// Special codes with their description, they are not languages
+const SPECIAL_LANGUAGES: &[(&str, &str)] = &[
+    ("und", "undetermined"),
+    ("mul", "multiple languages"),
+    ("zxx", "no linguistic content"),
+    ("mis", "uncoded language"),
+];
+
+/// Parsed BCP 47 language tag, the language is normalized ('fre', 'fra' and 'fr' are 'fr')
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LanguageTag {
+    pub language: String,
+    pub script: Option<String>,
+    pub region: Option<String>,
+    pub variants: Vec<String>,
+}
+
+impl LanguageTag {
+    /// Parse an ISO 639-2/B, ISO 639-2/T, ISO 639-1 code or a BCP 47 tag
+    pub fn parse(tag: &str) -> Self {
+        let mut subtags = tag.trim().split(['-', '_']);
+        let language = subtags.next().unwrap_or_default().to_ascii_lowercase();
+        let language = LANGUAGES
+            .iter()
+            .find(|(b, t, a)| language == *b || language == *t || (!a.is_empty() && language == *a))
+            .map(|(_, t, a)| if a.is_empty() { *t } else { *a })
+            .map(str::to_owned)
+            .unwrap_or(language);
+
+        let mut output = Self {
+            language,
+            script: None,
+            region: None,
+            variants: vec![],
+        };
+        for subtag in subtags {
+            let is_alpha = subtag.chars().all(|c| c.is_ascii_alphabetic());
+            let is_digit = subtag.chars().all(|c| c.is_ascii_digit());
+            let is_first = output.region.is_none() && output.variants.is_empty();
+
+            match subtag.len() {
+                4 if is_alpha && is_first && output.script.is_none() => {
+                    let (first, rest) = subtag.split_at(1);
+                    output.script = Some(format!(
+                        "{}{}",
+                        first.to_ascii_uppercase(),
+                        rest.to_ascii_lowercase()
+                    ));
+                }
+                2 if is_alpha && is_first => output.region = Some(subtag.to_ascii_uppercase()),
+                3 if is_digit && is_first => output.region = Some(subtag.to_owned()),
+                _ => output.variants.push(subtag.to_ascii_lowercase()),
+            }
+        }
+        output
+    }
+
+    /// Get the tag of a track, from its language ietf when known or from its language
+    pub fn from_track(language: &str, language_ietf: &str) -> Self {
+        match language_ietf {
+            "" | "und" => Self::parse(language),
+            language_ietf => Self::parse(language_ietf),
+        }
+    }
+
+    /// Get the description of a special code ('und', 'mul', 'zxx' or 'mis')
+    pub fn special(&self) -> Option<&'static str> {
+        SPECIAL_LANGUAGES
+            .iter()
+            .find(|(code, _)| *code == self.language)
+            .map(|(_, description)| *description)
+    }
+
+    /// Is the language undetermined
+    pub fn is_undetermined(&self) -> bool {
+        self.language == "und"
+    }
+
+    /// Is the tag in the 'range', every subtag of the range must be in the tag
+    /// ('en' matches 'en-US' and 'en-GB', 'zh-Hant' matches 'zh-Hant-TW' but not 'zh-Hans').
+    /// A special code only matches itself and is never matched by a language
+    pub fn matches(&self, range: &LanguageTag) -> bool {
+        self.language == range.language
+            && range
+                .script
+                .as_ref()
+                .is_none_or(|script| self.script.as_ref() == Some(script))
+            && range
+                .region
+                .as_ref()
+                .is_none_or(|region| self.region.as_ref() == Some(region))
+            && range
+                .variants
+                .iter()
+                .all(|variant| self.variants.contains(variant))
+    }
+}
+
+impl fmt::Display for LanguageTag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.language)?;
+        for subtag in self
+            .script
+            .iter()
+            .chain(self.region.iter())
+            .chain(self.variants.iter())
+        {
+            write!(f, "-{subtag}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Normalize an ISO 639-2/B, ISO 639-2/T, ISO 639-1 code or a BCP 47 tag
+pub fn normalize(code: &str) -> String {
+    LanguageTag::parse(code).to_string()
+}
+
+/// Get the canonical language of a track, from its language ietf when known or from its language
+pub fn canonical(language: &str, language_ietf: &str) -> String {
+    LanguageTag::from_track(language, language_ietf).to_string()
+}
+
+/// Get the ISO 639-2/B code of the language of a tag, for the legacy language of the tracks
+/// ('en-US' is 'eng'), none if the language is not a known code or a three-letter code
+pub fn legacy_code(tag: &str) -> Option<String> {
+    let language = LanguageTag::parse(tag).language;
+    match LANGUAGES
+        .iter()
+        .find(|(_, t, a)| language == *t || (!a.is_empty() && language == *a))
+    {
+        Some((b, _, _)) => Some(b.to_string()),
+        None if language.len() == 3 && language.chars().all(|c| c.is_ascii_lowercase()) => {
+            Some(language)
+        }
+        None => None,
+    }
+}
+
+/// Is the language of the track in the 'range' (e.g. 'en' matches a track in 'en-US'),
+/// the language is only read when the track has no language ietf
+pub fn matches_track(language: &str, language_ietf: &str, range: &str) -> bool {
+    LanguageTag::from_track(language, language_ietf).matches(&LanguageTag::parse(range))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize("fre"), "fr");
+        assert_eq!(normalize("fra"), "fr");
+        assert_eq!(normalize("fr"), "fr");
+        assert_eq!(normalize("GER"), "de");
+        assert_eq!(normalize("chi"), "zh");
+        assert_eq!(normalize("fil"), "fil");
+        assert_eq!(normalize("und"), "und");
+    }
+
+    #[test]
+    fn test_legacy_code() {
+        assert_eq!(legacy_code("en-US").as_deref(), Some("eng"));
+        assert_eq!(legacy_code("fra").as_deref(), Some("fre"));
+        assert_eq!(legacy_code("yue-Hant").as_deref(), Some("yue"));
+        assert_eq!(legacy_code("zxx").as_deref(), Some("zxx"));
+        assert_eq!(legacy_code("english"), None);
+        assert_eq!(legacy_code(""), None);
+    }
+
+    #[test]
+    fn test_normalize_tag() {
+        assert_eq!(normalize("pt-br"), "pt-BR");
+        assert_eq!(normalize("zh-hant-TW"), "zh-Hant-TW");
+        assert_eq!(normalize("es-419"), "es-419");
+        assert_eq!(normalize("zho_Hans"), "zh-Hans");
+    }
+
+    #[test]
+    fn test_canonical() {
+        assert_eq!(canonical("ger", "und"), "de");
+        assert_eq!(canonical("ger", "de-AT"), "de-AT");
+        assert_eq!(canonical("und", "ja"), "ja");
+    }
+
+    #[test]
+    fn test_parse() {
+        let tag = LanguageTag::parse("zh-hant-tw");
+        assert_eq!(tag.language, "zh");
+        assert_eq!(tag.script.as_deref(), Some("Hant"));
+        assert_eq!(tag.region.as_deref(), Some("TW"));
+        assert!(tag.variants.is_empty());
+
+        let tag = LanguageTag::parse("de-CH-1996");
+        assert_eq!(tag.region.as_deref(), Some("CH"));
+        assert_eq!(tag.variants, vec!["1996"]);
+        assert_eq!(tag.to_string(), "de-CH-1996");
+    }
+
+    #[test]
+    fn test_matches() {
+        let range = LanguageTag::parse("en");
+        assert!(LanguageTag::parse("en-US").matches(&range));
+        assert!(LanguageTag::parse("en-GB").matches(&range));
+        assert!(LanguageTag::parse("eng").matches(&range));
+        assert!(!LanguageTag::parse("en").matches(&LanguageTag::parse("en-US")));
+
+        let range = LanguageTag::parse("zh-Hant");
+        assert!(LanguageTag::parse("zh-Hant-TW").matches(&range));
+        assert!(!LanguageTag::parse("zh-Hans").matches(&range));
+
+        let range = LanguageTag::parse("pt-BR");
+        assert!(LanguageTag::parse("pt-BR").matches(&range));
+        assert!(!LanguageTag::parse("pt-PT").matches(&range));
+    }
+
+    #[test]
+    fn test_special() {
+        assert_eq!(LanguageTag::parse("und").special(), Some("undetermined"));
+        assert_eq!(
+            LanguageTag::parse("zxx").special(),
+            Some("no linguistic content")
+        );
+        assert_eq!(LanguageTag::parse("en").special(), None);
+        assert!(!LanguageTag::parse("mul").matches(&LanguageTag::parse("en")));
+        assert!(LanguageTag::parse("mul").matches(&LanguageTag::parse("mul")));
+    }
+
+    #[test]
+    fn test_matches_track() {
+        assert!(matches_track("eng", "en-US", "en"));
+        assert!(matches_track("eng", "en-US", "eng"));
+        assert!(matches_track("chi", "zh-Hant", "zh-Hant"));
+        assert!(!matches_track("chi", "zh-Hans", "zh-Hant"));
+        assert!(matches_track("jpn", "und", "ja"));
+        assert!(!matches_track("jpn", "en-US", "ja"));
+    }
+}
@@ -1,131 +1,415 @@
-use std::process::Command;
-use std::{fmt, fs};
+use std::path::{Path, PathBuf};
+use std::process::{self, Command};
+use std::time::{Duration, Instant};
+use std::{fmt, io};
 
+mod accessibility;
+mod audio;
+mod cli;
+mod codec;
+mod config;
 mod deserialize;
 mod error;
+mod files;
+mod language;
 mod matroska;
+mod outcome;
+mod plan;
+mod policy;
+mod preference;
+mod remux;
+mod report;
+mod rule;
 mod same;
+mod undetermined;
+mod video;
 
+use clap::Parser;
 use dialoguer::console::Term;
-use dialoguer::{theme::ColorfulTheme, Select};
+use dialoguer::{theme::ColorfulTheme, Input, Select};
 
-use crate::error::TempResult;
+use crate::accessibility::plan_accessibility;
+use crate::cli::{Cli, PlanFormat, ReportFormat};
+use crate::config::Config;
+use crate::error::{TempError, TempResult};
+use crate::files::get_files;
+use crate::language::LanguageTag;
 use crate::matroska::*;
-use crate::same::Same;
+use crate::outcome::Outcome;
+use crate::plan::{
+    apply_forced, merge_edits, plan_unforced, Backend, FilePlan, Flag, ForcedMode, Selector,
+    TrackEdit,
+};
+use crate::policy::Policies;
+use crate::preference::{resolve, Preference, NONE_LANGUAGE, ORIGINAL_LANGUAGE};
+use crate::report::{FileReport, Report};
+use crate::rule::{find_rule, resolved_audio};
+use crate::same::{Coverage, Same};
+use crate::undetermined::{
+    assign_languages, get_und_groups, plan_languages, validate_language, UndGroup,
+};
 
-fn main() -> TempResult {
-    let mkvs = get_files_to_matroska(get_files())?;
+fn main() {
+    let cli = match Cli::try_parse() {
+        Ok(cli) => cli,
+        Err(err) => {
+            let _ = err.print();
+            // '--help' and '--version' are not errors
+            process::exit(match err.use_stderr() {
+                true => Outcome::Usage.code(),
+                false => Outcome::Success.code(),
+            });
+        }
+    };
+
+    let outcome = match run(cli) {
+        Ok(outcome) => outcome,
+        Err(err) => {
+            eprintln!("Error: {err}");
+            let mut source = std::error::Error::source(&err);
+            while let Some(cause) = source {
+                eprintln!("Caused by: {cause}");
+                source = cause.source();
+            }
+            if let Some(hint) = err.hint() {
+                eprintln!("Hint: {hint}");
+            }
+            err.outcome()
+        }
+    };
+    process::exit(outcome.code());
+}
+
+fn run(cli: Cli) -> TempResult<Outcome> {
+    let (mkvs, mut result_commands) =
+        get_files_to_matroska(get_files(&cli.paths, &cli.walk_options())?)?;
 
     if mkvs.is_empty() {
-        println!("Unable to find any '.mkv' files");
-        return Ok(());
+        if result_commands.is_empty() {
+            println!("Unable to find any '.mkv' files");
+            return Ok(Outcome::NothingToDo);
+        }
+        print_results(&cli, &result_commands, Report::default())?;
+        return Ok(Outcome::from_results(&result_commands));
     }
 
-    let same_subs: Vec<Same> = get_same_languages(&mkvs, MatroskaTrackType::Subtitles);
-    let same_subs: Vec<Same> = same_subs
-        .clone()
-        .into_iter()
-        .chain(
-            get_same_languages_ietf(&mkvs, MatroskaTrackType::Subtitles)
-                .into_iter()
-                .filter(|inner| !same_subs.contains(inner)),
-        )
-        .collect();
+    let config = Config::load(cli.config.as_deref())?;
 
-    let same_audios: Vec<Same> = get_same_languages(&mkvs, MatroskaTrackType::Audio);
-    let same_audios: Vec<Same> = same_audios
-        .clone()
-        .into_iter()
-        .chain(
-            get_same_languages_ietf(&mkvs, MatroskaTrackType::Audio)
-                .into_iter()
-                .filter(|inner| !same_audios.contains(inner)),
-        )
+    // The policies apply when the tracks are not given on the command line,
+    // the files under a policy that can't be read are reported and left unchanged
+    let mut policies = Policies::default();
+    let mut readable = vec![];
+    let mut file_policies = vec![];
+    let mut file_profiles = vec![];
+    for matroska in mkvs {
+        let policy = match cli.uses_policies() {
+            true => policies.get(Path::new(&matroska.path)),
+            false => Ok(None),
+        };
+        // A flag tagging run only reads the original language of the policies
+        let profile = match (cli.is_interactive(), &policy) {
+            (true, Ok(Some(policy))) => policy.to_profile(&config).map(Some),
+            _ => Ok(None),
+        };
+        match (policy, profile) {
+            (Ok(policy), Ok(profile)) => {
+                readable.push(matroska);
+                file_policies.push(policy);
+                file_profiles.push(profile);
+            }
+            (Err(err), _) | (_, Err(err)) => {
+                result_commands.push(ResultCommand::Error(matroska.path, err.to_string()))
+            }
+        }
+    }
+    let mut mkvs = readable;
+
+    // The undetermined tracks are tagged first, so their languages can be chosen as default
+    let assignments = match cli.tags_languages() {
+        true => choose_languages(&cli, &get_und_groups(&mkvs))?,
+        false => vec![],
+    };
+    let tagged = assign_languages(&mut mkvs, &assignments);
+
+    let unmanaged: Vec<&Matroska> = mkvs
+        .iter()
+        .zip(&file_profiles)
+        .filter(|(_, profile)| profile.is_none())
+        .map(|(matroska, _)| matroska)
         .collect();
 
-    let selection = Select::with_theme(&ColorfulTheme::default())
-        .with_prompt("> Please choose the subtitle track:")
-        .items(&same_subs)
-        .default(0)
-        .interact_on_opt(&Term::stderr())
-        .unwrap();
+    let subtitle_preferences = cli.subtitle_preferences();
+    let audio_preferences = cli.audio_preferences();
 
-    let choosen_sub = match selection {
-        Some(i) => same_subs.get(i),
-        None => None,
+    let profile = match (&cli.profile, &config.default_profile) {
+        (Some(name), _) => Some(config.profile(name)?),
+        (None, Some(name)) if cli.is_interactive() => Some(config.profile(name)?),
+        (None, _) => None,
     };
 
-    let selection = Select::with_theme(&ColorfulTheme::default())
-        .with_prompt("> Please choose the audio track:")
-        .items(&same_audios)
-        .default(0)
-        .interact_on_opt(&Term::stderr())
-        .unwrap();
-
-    let choosen_audio = match selection {
-        Some(i) => same_audios.get(i),
-        None => None,
+    let (choosen_sub, choosen_audio) = match profile {
+        _ if unmanaged.is_empty() => (None, None),
+        Some(profile) => (
+            Selector::from_preferences(&profile.subtitle),
+            Selector::from_preferences(&profile.audio),
+        ),
+        None if cli.is_interactive() => (
+            choose_with_coverage(
+                "> Please choose the subtitle track:",
+                &unmanaged,
+                MatroskaTrackType::Subtitles,
+                true,
+            )?,
+            choose_with_coverage(
+                "> Please choose the audio track:",
+                &unmanaged,
+                MatroskaTrackType::Audio,
+                false,
+            )?,
+        ),
+        None => (
+            match cli.subtitle.as_deref() {
+                Some(NONE_LANGUAGE) => Some(Selector::Disabled),
+                _ => find_common(
+                    &unmanaged,
+                    MatroskaTrackType::Subtitles,
+                    &subtitle_preferences,
+                )?,
+            },
+            match cli.audio.as_deref() {
+                Some(ORIGINAL_LANGUAGE) => Selector::from_preferences(&audio_preferences),
+                _ => find_common(&unmanaged, MatroskaTrackType::Audio, &audio_preferences)?,
+            },
+        ),
     };
 
-    let mut result_commands = vec![];
-    for matroska in &mkvs {
-        let subtitle_args = match choosen_sub {
-            Some(sub) => {
-                if sub.language_ietf == "und" {
-                    generate_args_by_language(
-                        matroska.get_subtitles(),
-                        &sub.language,
-                        sub.name.to_owned(),
-                    )
-                } else {
-                    generate_args_by_language_ietf(
-                        matroska.get_subtitles(),
-                        &sub.language_ietf,
-                        sub.name.to_owned(),
-                    )
-                }
+    // The video is only prompted when a file has several video tracks
+    let video_preferences = cli.video_preferences();
+    let choosen_video = match profile {
+        _ if unmanaged.is_empty() => None,
+        Some(profile) => Selector::from_preferences(&profile.video),
+        None if cli.is_interactive() => {
+            match unmanaged
+                .iter()
+                .any(|matroska| matroska.get_videos().len() > 1)
+            {
+                true => choose_with_coverage(
+                    "> Please choose the video track:",
+                    &unmanaged,
+                    MatroskaTrackType::Video,
+                    false,
+                )?,
+                false => None,
             }
-            None => String::new(),
+        }
+        None => Selector::from_preferences(&video_preferences),
+    };
+
+    let mut plans = vec![];
+    let mut report = Report::default();
+    for (((matroska, file_profile), file_policy), tagged) in mkvs
+        .iter()
+        .zip(&file_profiles)
+        .zip(&file_policies)
+        .zip(&tagged)
+    {
+        let (choosen_sub, choosen_audio, choosen_video, forced, rules) = match file_profile {
+            Some(profile) => (
+                Selector::from_preferences(&profile.subtitle),
+                Selector::from_preferences(&profile.audio),
+                Selector::from_preferences(&profile.video),
+                profile.forced,
+                profile.rules.as_slice(),
+            ),
+            None => (
+                choosen_sub.clone(),
+                choosen_audio.clone(),
+                choosen_video.clone(),
+                profile.and_then(|profile| profile.forced),
+                profile.map_or(&[][..], |profile| profile.rules.as_slice()),
+            ),
         };
 
-        let audio_args = match choosen_audio {
-            Some(audio) => {
-                if audio.language_ietf == "und" {
-                    generate_args_by_language(
-                        matroska.get_audios(),
-                        &audio.language,
-                        audio.name.to_owned(),
-                    )
-                } else {
-                    generate_args_by_language_ietf(
-                        matroska.get_audios(),
-                        &audio.language_ietf,
-                        audio.name.to_owned(),
-                    )
-                }
-            }
-            None => String::new(),
+        let mut edits = vec![];
+        if let Some(audio) = choosen_audio {
+            edits.extend(audio.plan(matroska.get_audios()));
+        }
+
+        // The subtitle depends on the audio that is default once the file is edited
+        let audio = resolved_audio(&edits, &matroska.get_audios());
+        let (choosen_sub, forced) = match find_rule(cli.subtitle_rules.iter().chain(rules), audio) {
+            Some(rule) => (
+                Selector::from_preferences(&rule.subtitle),
+                cli.forced.or(rule.forced).or(forced),
+            ),
+            None => (choosen_sub, cli.forced.or(forced)),
+        };
+        let forced = forced.unwrap_or(ForcedMode::Keep);
+        if let Some(sub) = choosen_sub {
+            let mut sub_edits = sub.plan(matroska.get_subtitles());
+            apply_forced(&mut sub_edits, forced);
+            edits.extend(sub_edits);
+        }
+        // The forced flags are cleared even when no subtitle is chosen or matches
+        if forced == ForcedMode::Clear {
+            merge_edits(&mut edits, plan_unforced(matroska.get_subtitles()));
+        }
+        if let Some(video) = choosen_video {
+            edits.extend(video.plan(matroska.get_videos()));
+        }
+        if cli.tag_accessibility {
+            merge_edits(
+                &mut edits,
+                plan_accessibility(matroska.tracks.iter().collect()),
+            );
+        }
+        let original = match &cli.tag_original {
+            Some(Some(language)) => Some(language),
+            Some(None) => file_policy
+                .as_ref()
+                .and_then(|policy| policy.original.as_ref()),
+            None => None,
         };
+        if let Some(language) = original {
+            let mut tracks = matroska.get_audios();
+            tracks.extend(matroska.get_subtitles());
+            merge_edits(&mut edits, plan_original(tracks, language));
+        }
 
-        if subtitle_args.is_empty() && audio_args.is_empty() {
-            break;
+        merge_edits(
+            &mut edits,
+            plan_languages(tagged.iter().map(|&i| &matroska.tracks[i]).collect()),
+        );
+
+        if edits.is_empty() {
+            result_commands.push(ResultCommand::Skipped(matroska.path.to_owned()));
+            continue;
+        }
+
+        let mut plan = FilePlan {
+            tracks: &matroska.tracks,
+            ..FilePlan::new(&matroska.path, edits)
+        };
+        if cli.remux {
+            plan.backend = Backend::Remux(&matroska.tracks);
+        }
+        // A track mkvpropedit can't select could be mistaken for another one, the file is left untouched
+        if let Err(err) = plan.command() {
+            report
+                .files
+                .push(FileReport::failed(&plan, &err, Duration::ZERO));
+            result_commands.push(ResultCommand::Error(
+                matroska.path.to_owned(),
+                err.to_string(),
+            ));
+            continue;
+        }
+        plans.push(plan);
+    }
+
+    if cli.dry_run {
+        for plan in &plans {
+            match cli.plan_format {
+                PlanFormat::Shell => println!("{}", plan.to_text()?),
+                PlanFormat::Json => println!("{}", plan.to_json()?),
+            }
+        }
+        print_results(&cli, &result_commands, report)?;
+        // The planned files count as edited, the other results are the ones of a real run
+        let planned = plans
+            .iter()
+            .map(|plan| ResultCommand::Success(plan.path.to_owned()));
+        let results: Vec<ResultCommand> = planned.chain(result_commands).collect();
+        return Ok(Outcome::from_results(&results));
+    }
+
+    // The free space of every remux is checked before the first one, so a batch is not stopped halfway
+    let mut runnable = vec![];
+    for plan in &plans {
+        if let Backend::Remux(_) = plan.backend {
+            if let Err(err) = remux::check_file_space(Path::new(plan.path)) {
+                report
+                    .files
+                    .push(FileReport::failed(plan, &err, Duration::ZERO));
+                result_commands.push(ResultCommand::Error(plan.path.to_owned(), err.to_string()));
+                continue;
+            }
         }
+        runnable.push(plan);
+    }
 
-        let mut command = generate_command(&matroska.path, &[&audio_args, &subtitle_args]);
-        let command = command.output().unwrap();
+    for plan in runnable {
+        let start = Instant::now();
+        // A remux that returns has succeeded, even if mkvmerge warned
+        let (command, success) = match plan.backend {
+            Backend::Propedit => {
+                let output = run_tool(&mut plan.command()?)?;
+                let success = output.status.success();
+                (output, success)
+            }
+            Backend::Remux(tracks) => match remux::remux(plan, tracks) {
+                Ok(output) => (output, true),
+                Err(err @ TempError::ToolNotFound(_)) => return Err(err),
+                // The original of a failed remux is kept, the next files are still remuxed
+                Err(err) => {
+                    report
+                        .files
+                        .push(FileReport::failed(plan, &err, start.elapsed()));
+                    result_commands
+                        .push(ResultCommand::Error(plan.path.to_owned(), err.to_string()));
+                    continue;
+                }
+            },
+        };
+        report
+            .files
+            .push(FileReport::new(plan, &command, success, start.elapsed()));
 
-        match &command.status.success() {
-            true => result_commands.push(ResultCommand::Success(matroska.path.to_owned())),
+        match success {
+            true => result_commands.push(ResultCommand::Success(plan.path.to_owned())),
             false => result_commands.push(ResultCommand::Error(
-                matroska.path.to_owned(),
+                plan.path.to_owned(),
                 String::from_utf8_lossy(&command.stderr).to_string(),
             )),
         }
     }
 
-    println!(">> Result:");
-    result_commands.iter().for_each(|r| println!("{}", r));
+    print_results(&cli, &result_commands, report)?;
+
+    Ok(Outcome::from_results(&result_commands))
+}
 
+/// Print the results and write the report
+fn print_results(cli: &Cli, result_commands: &[ResultCommand], mut report: Report) -> TempResult {
+    report.add_results(result_commands);
+
+    if let Some(path) = &cli.report_file {
+        report.write(path)?;
+    }
+
+    match cli.report {
+        ReportFormat::Text => {
+            let sections = [
+                (
+                    ">> Result:",
+                    ResultCommand::is_success as fn(&ResultCommand) -> bool,
+                ),
+                (">> Unable to identify:", ResultCommand::is_identify_error),
+                (">> Skipped (no matching track):", ResultCommand::is_skipped),
+                (">> Unable to edit:", ResultCommand::is_error),
+            ];
+            for (title, filter) in sections {
+                let results: Vec<&ResultCommand> =
+                    result_commands.iter().filter(|r| filter(r)).collect();
+                if results.is_empty() {
+                    continue;
+                }
+                println!("{title}");
+                results.iter().for_each(|r| println!("{}", r));
+            }
+        }
+        ReportFormat::Json => println!("{}", report.to_json()?),
+    }
     Ok(())
 }
 
@@ -133,6 +417,30 @@ fn main() -> TempResult {
 pub enum ResultCommand {
     Success(String),
     Error(String, String),
+    Skipped(String),
+    IdentifyError(String, String),
+}
+
+impl ResultCommand {
+    /// Has the file been edited
+    pub fn is_success(&self) -> bool {
+        matches!(self, ResultCommand::Success(_))
+    }
+
+    /// Has mkvpropedit failed
+    pub fn is_error(&self) -> bool {
+        matches!(self, ResultCommand::Error(_, _))
+    }
+
+    /// Has the file been skipped because no track matches
+    pub fn is_skipped(&self) -> bool {
+        matches!(self, ResultCommand::Skipped(_))
+    }
+
+    /// Has mkvmerge failed to identify the file
+    pub fn is_identify_error(&self) -> bool {
+        matches!(self, ResultCommand::IdentifyError(_, _))
+    }
 }
 
 impl fmt::Display for ResultCommand {
@@ -140,76 +448,230 @@ impl fmt::Display for ResultCommand {
         match &self {
             ResultCommand::Success(path) => write!(f, "Success {path}"),
             ResultCommand::Error(path, err) => write!(f, "Error {path} ({err})"),
+            ResultCommand::Skipped(path) => write!(f, "Skipped {path}"),
+            ResultCommand::IdentifyError(path, err) => write!(f, "Error {path} ({err})"),
         }
     }
 }
 
-/// Get the same languages
-pub fn get_same_languages(mkvs: &Vec<Matroska>, track_type: MatroskaTrackType) -> Vec<Same> {
-    let mut output: Vec<Same> = vec![];
-    for matroska in mkvs {
-        let tracks = match track_type {
-            MatroskaTrackType::Audio => get_tracks_languages(matroska.get_audios()),
-            MatroskaTrackType::Video => get_tracks_languages(matroska.get_videos()),
-            MatroskaTrackType::Subtitles => get_tracks_languages(matroska.get_subtitles()),
+/// Prompt the user to choose a track among the files, then a fallback track for the files
+/// without it, the subtitles can also be disabled
+pub fn choose_with_coverage(
+    prompt: &str,
+    mkvs: &[&Matroska],
+    track_type: MatroskaTrackType,
+    allow_disable: bool,
+) -> TempResult<Option<Selector<'static>>> {
+    let mut chosen: Vec<Same> = vec![];
+    let mut remaining: Vec<&Matroska> = mkvs.to_vec();
+    let mut prompt = prompt.to_owned();
+
+    loop {
+        let coverage = get_coverage(&remaining, track_type.clone());
+        if coverage.is_empty() {
+            break;
+        }
+
+        let disable = allow_disable && chosen.is_empty();
+        let items: Vec<String> = coverage
+            .iter()
+            .map(|coverage| coverage.to_string())
+            .chain(disable.then(|| "None (disable all)".to_string()))
+            .collect();
+
+        let same = match prompt_items(&prompt, &items)? {
+            Some(i) if i == coverage.len() => return Ok(Some(Selector::Disabled)),
+            Some(i) => coverage[i].same.to_owned(),
+            None => break,
         };
 
-        if output.is_empty() {
-            output = tracks;
-            continue;
+        remaining.retain(|matroska| {
+            !matroska
+                .get_tracks(&track_type)
+                .iter()
+                .any(|track| same.matches_track(track))
+        });
+        chosen.push(same.clone());
+
+        if remaining.is_empty() {
+            break;
         }
 
-        output.retain(|outer| {
-            tracks
+        eprintln!("These files don't have this {track_type} track:");
+        remaining.iter().for_each(|matroska| {
+            let similar = matroska
+                .get_tracks(&track_type)
                 .iter()
-                .any(|inner| inner.language == outer.language && inner.name == outer.name)
+                .any(|track| same.is_similar_track(track));
+            match similar {
+                true => eprintln!(
+                    "    {} (closest track of this language and name without a fallback)",
+                    matroska.path
+                ),
+                false => eprintln!("    {} (left unchanged)", matroska.path),
+            }
         });
+        prompt = format!("> Please choose a fallback {track_type} track for these files:");
     }
-    output
-}
 
-/// Get the tracks with the language field for the Same struct
-pub fn get_tracks_languages(tracks: Vec<&MatroskaTrack>) -> Vec<Same> {
-    tracks
-        .iter()
-        .filter(|track| track.language != "und")
-        .map(|track| Same::new(&track.language, &track.language_ietf, track.name.to_owned()))
-        .collect()
+    Ok(match chosen.is_empty() {
+        true => None,
+        false => Some(Selector::Same(chosen)),
+    })
 }
 
-/// Get the same languages_ietf
-pub fn get_same_languages_ietf(mkvs: &Vec<Matroska>, track_type: MatroskaTrackType) -> Vec<Same> {
-    let mut output: Vec<Same> = vec![];
+/// Get every candidate of the files with the number of files having it, the most common first
+pub fn get_coverage(mkvs: &[&Matroska], track_type: MatroskaTrackType) -> Vec<Coverage> {
+    let mut union: Vec<Same> = vec![];
     for matroska in mkvs {
-        let tracks = match track_type {
-            MatroskaTrackType::Audio => get_tracks_languages_ieft(matroska.get_audios()),
-            MatroskaTrackType::Video => get_tracks_languages_ieft(matroska.get_videos()),
-            MatroskaTrackType::Subtitles => get_tracks_languages_ieft(matroska.get_subtitles()),
-        };
-
-        if output.is_empty() {
-            output = tracks;
-            continue;
+        let tracks = matroska.get_tracks(&track_type);
+        for same in get_tracks_languages(tracks.clone())
+            .into_iter()
+            .chain(get_tracks_languages_ieft(tracks))
+        {
+            if !union.iter().any(|other| other.is_equivalent(&same)) {
+                union.push(same);
+            }
         }
+    }
 
-        output.retain(|outer| {
-            tracks
+    let mut coverage: Vec<Coverage> = union
+        .into_iter()
+        .map(|same| Coverage {
+            count: mkvs
                 .iter()
-                .any(|inner| inner.language_ietf == outer.language_ietf && inner.name == outer.name)
-        });
+                .filter(|matroska| {
+                    matroska
+                        .get_tracks(&track_type)
+                        .iter()
+                        .any(|track| same.matches_track(track))
+                })
+                .count(),
+            total: mkvs.len(),
+            same,
+        })
+        .collect();
+    // The commentaries come last so that they are never the first choice
+    coverage.sort_by_key(|coverage| (coverage.same.commentary, std::cmp::Reverse(coverage.count)));
+    coverage
+}
+
+/// Get the terminal of the prompts, the prompts are written to stderr
+fn prompt_term() -> TempResult<Term> {
+    let term = Term::stderr();
+    match term.is_term() {
+        true => Ok(term),
+        false => Err(TempError::NoTerminal(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "stderr is not a terminal",
+        ))),
     }
-    output
+}
+
+/// Prompt the user to choose one of the 'items'
+fn prompt_items(prompt: &str, items: &[String]) -> TempResult<Option<usize>> {
+    let term = prompt_term()?;
+    Select::with_theme(&ColorfulTheme::default())
+        .with_prompt(prompt)
+        .items(items)
+        .default(0)
+        .interact_on_opt(&term)
+        .map_err(TempError::NoTerminal)
+}
+
+/// Get the language of each group of undetermined tracks, from the first matching rule or from the prompt,
+/// the groups without a language are left undetermined
+pub fn choose_languages(cli: &Cli, groups: &[UndGroup]) -> TempResult<Vec<(UndGroup, String)>> {
+    let mut assignments = vec![];
+    for group in groups {
+        let language = match cli.language_rules.iter().find(|rule| rule.matches(group)) {
+            Some(rule) => Some(rule.language.to_owned()),
+            None if cli.tag_language => prompt_language(&format!(
+                "> Language of the undetermined tracks {group} (empty to leave them)"
+            ))?,
+            None => None,
+        };
+        if let Some(language) = language {
+            assignments.push((group.to_owned(), language));
+        }
+    }
+    Ok(assignments)
+}
+
+/// Prompt the user for a language, none if the answer is empty
+fn prompt_language(prompt: &str) -> TempResult<Option<String>> {
+    let term = prompt_term()?;
+    let language: String = Input::with_theme(&ColorfulTheme::default())
+        .with_prompt(prompt)
+        .allow_empty(true)
+        .validate_with(|input: &String| match input.trim().is_empty() {
+            true => Ok(()),
+            false => validate_language(input.trim()),
+        })
+        .interact_text_on(&term)
+        .map_err(TempError::NoTerminal)?;
+
+    Ok(match language.trim() {
+        "" => None,
+        language => Some(language.to_owned()),
+    })
+}
+
+/// Get the selector of the track given on the command line, the track must be in every file
+/// but its language is a range, so the files can have it in different regions (e.g. 'en' in 'en-US' and 'en-GB')
+pub fn find_common<'a>(
+    mkvs: &[&Matroska],
+    track_type: MatroskaTrackType,
+    preferences: &'a [Preference],
+) -> TempResult<Option<Selector<'a>>> {
+    let Some(preference) = preferences.first() else {
+        return Ok(None);
+    };
+
+    match mkvs
+        .iter()
+        .all(|matroska| resolve(preferences, &matroska.get_tracks(&track_type)).is_some())
+    {
+        true => Ok(Selector::from_preferences(preferences)),
+        false => {
+            let wanted = match &preference.name {
+                Some(name) => format!("{} ({name})", preference.language),
+                None => preference.language.to_owned(),
+            };
+            Err(TempError::TrackNotFound(track_type, wanted))
+        }
+    }
+}
+
+/// Get the tracks with the language field for the Same struct, the videos are kept without a language
+pub fn get_tracks_languages(tracks: Vec<&MatroskaTrack>) -> Vec<Same> {
+    tracks
+        .iter()
+        .filter(|track| track.is_video() || track.language != "und")
+        .map(|track| Same::from_track(track))
+        .collect()
 }
 
 /// Get the tracks with the language_ieft field for the Same struct
 pub fn get_tracks_languages_ieft(tracks: Vec<&MatroskaTrack>) -> Vec<Same> {
     tracks
         .iter()
-        .filter(|track| track.language_ietf != "und")
-        .map(|track| Same::new(&track.language, &track.language_ietf, track.name.to_owned()))
+        .filter(|track| !LanguageTag::parse(&track.language_ietf).is_undetermined())
+        .map(|track| Same::from_track(track))
         .collect()
 }
 
+/// Run the command and collect its output
+pub fn run_tool(command: &mut Command) -> TempResult<process::Output> {
+    command.output().map_err(|err| {
+        let program = command.get_program().to_string_lossy().to_string();
+        match err.kind() {
+            io::ErrorKind::NotFound => TempError::ToolNotFound(program),
+            _ => TempError::Io(PathBuf::from(program), err),
+        }
+    })
+}
+
 /// Generate the command
 pub fn generate_command(path: &str, args: &[&str]) -> Command {
     let mut command = Command::new("mkvpropedit");
@@ -220,71 +682,131 @@ pub fn generate_command(path: &str, args: &[&str]) -> Command {
     command
 }
 
-/// Generate the args with the language
-pub fn generate_args_by_language(
-    tracks: Vec<&MatroskaTrack>,
-    language: &str,
-    name: Option<String>,
-) -> String {
-    tracks.iter().fold("".to_string(), |acc, track| {
-        format!(
-            "{acc} --edit track:{} --set flag-default={}",
-            track.id + 1,
-            (track.language == language && track.name.as_deref() == name.as_deref()) as u8
-        )
-    })
+/// Keep the edits only if one of the tracks matches
+pub fn plan_matched(edits: Vec<TrackEdit>) -> Vec<TrackEdit> {
+    match edits.iter().any(|edit| edit.default == Some(true)) {
+        true => edits,
+        false => vec![],
+    }
 }
 
-/// Generate the args with the language ietf
-pub fn generate_args_by_language_ietf(
-    tracks: Vec<&MatroskaTrack>,
-    language_ietf: &str,
-    name: Option<String>,
-) -> String {
-    tracks.iter().fold("".to_string(), |acc, track| {
-        format!(
-            "{acc} --edit track:{} --set flag-default={}",
-            track.id + 1,
-            (track.language_ietf == language_ietf && track.name.as_deref() == name.as_deref())
-                as u8
-        )
-    })
+/// Plan the default flags with the chosen track
+pub fn plan_by_track<'a>(
+    tracks: Vec<&'a MatroskaTrack>,
+    chosen: &MatroskaTrack,
+) -> Vec<TrackEdit<'a>> {
+    tracks
+        .into_iter()
+        .map(|track| TrackEdit::new(track, std::ptr::eq(track, chosen)))
+        .collect()
 }
 
-/// Get the files from the 'paths' and parse the mkv files to the 'Matroska' struct
-pub fn get_files_to_matroska(paths: Vec<fs::DirEntry>) -> TempResult<Vec<Matroska>> {
+/// Plan the default flags so that no track is default
+pub fn plan_disabled<'a>(tracks: Vec<&'a MatroskaTrack>) -> Vec<TrackEdit<'a>> {
+    tracks
+        .into_iter()
+        .map(|track| TrackEdit::new(track, false))
+        .collect()
+}
+
+/// Plan the original flags: set on the tracks in the language and cleared on the others
+pub fn plan_original<'a>(tracks: Vec<&'a MatroskaTrack>, language: &str) -> Vec<TrackEdit<'a>> {
+    tracks
+        .into_iter()
+        .map(|track| {
+            let original = language::matches_track(&track.language, &track.language_ietf, language);
+            TrackEdit {
+                flags: vec![(Flag::Original, original)],
+                ..TrackEdit::untouched(track)
+            }
+        })
+        .filter(|edit| edit.flags[0].1 != edit.track.original)
+        .collect()
+}
+
+/// Plan the default flags with the track of the 'same' candidate, no track is default if none matches
+pub fn plan_by_same<'a>(tracks: Vec<&'a MatroskaTrack>, same: &Same) -> Vec<TrackEdit<'a>> {
+    match tracks
+        .iter()
+        .copied()
+        .find(|track| same.matches_track(track))
+    {
+        Some(chosen) => plan_by_track(tracks, chosen),
+        None => plan_disabled(tracks),
+    }
+}
+
+/// Plan the default flags with the track of the language and name of the candidate
+/// closest to it, for the files without the candidate itself
+pub fn plan_by_similar<'a>(tracks: Vec<&'a MatroskaTrack>, same: &Same) -> Vec<TrackEdit<'a>> {
+    match tracks
+        .iter()
+        .copied()
+        .filter(|track| same.is_similar_track(track))
+        .min_by_key(|track| same.rank(track))
+    {
+        Some(chosen) => plan_by_track(tracks, chosen),
+        None => plan_disabled(tracks),
+    }
+}
+
+/// Get the files from the 'paths' and parse the mkv files to the 'Matroska' struct,
+/// the files that can't be identified are returned as results
+pub fn get_files_to_matroska(
+    paths: Vec<PathBuf>,
+) -> TempResult<(Vec<Matroska>, Vec<ResultCommand>)> {
     let mut mkvs: Vec<Matroska> = vec![];
+    let mut failures: Vec<ResultCommand> = vec![];
     for path in paths {
-        let display_path = path.path().display().to_string();
-
-        match path.path().extension() {
+        match path.extension() {
             Some(extension) if extension == "mkv" => {}
             Some(_) => continue,
             None => continue,
         }
 
-        let command = Command::new("mkvmerge")
+        match identify_file(&path) {
+            Ok(matroska) => mkvs.push(matroska),
+            Err(err @ TempError::ToolNotFound(_)) => return Err(err),
+            Err(err) => failures.push(ResultCommand::IdentifyError(
+                path.display().to_string(),
+                err.to_string(),
+            )),
+        }
+    }
+    Ok((mkvs, failures))
+}
+
+/// Identify the file with mkvmerge
+pub fn identify_file(path: &Path) -> TempResult<Matroska> {
+    let display_path = path.display().to_string();
+
+    let command = run_tool(
+        Command::new("mkvmerge")
             .arg("-F")
             .arg("json")
             .arg("--identify")
-            .arg(&display_path)
-            .output()
-            .unwrap();
+            .arg(&display_path),
+    )?;
 
-        let stdout = String::from_utf8_lossy(&command.stdout).to_string();
-        let matroska = Matroska::from_string(&display_path, stdout)?;
+    let stdout = String::from_utf8_lossy(&command.stdout).to_string();
+    let matroska = Matroska::from_string(&display_path, stdout.to_owned());
 
-        mkvs.push(matroska);
+    // mkvmerge exits with '1' on warnings and '2' on errors
+    if !matches!(command.status.code(), Some(0) | Some(1)) {
+        return Err(match matroska {
+            Err(err @ TempError::UnrecognizedFile(_)) => err,
+            _ => {
+                let stderr = String::from_utf8_lossy(&command.stderr);
+                let stderr = match stderr.trim().is_empty() {
+                    true => stdout.trim().to_owned(),
+                    false => stderr.trim().to_owned(),
+                };
+                TempError::ToolFailed("mkvmerge".to_string(), command.status, stderr)
+            }
+        });
     }
-    Ok(mkvs)
-}
 
-/// Get the files of the current directory
-pub fn get_files() -> Vec<fs::DirEntry> {
-    let mut paths: Vec<_> = fs::read_dir(".").unwrap().filter_map(|r| r.ok()).collect();
-
-    paths.sort_by_key(|dir| dir.path());
-    paths
+    matroska
 }
 
 #[cfg(test)]
@@ -292,77 +814,128 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test_generate_args_by_language_ietf() {
-        let tracks = vec![
-            MatroskaTrack {
-                id: 1,
-                name: Some("Track 1".to_string()),
-                type_: MatroskaTrackType::Audio,
-                default: false,
-                language: "eng".to_string(),
-                language_ietf: "en".to_string(),
-            },
-            MatroskaTrack {
-                id: 2,
-                name: Some("Track 2".to_string()),
-                type_: MatroskaTrackType::Video,
-                default: false,
-                language: "fre".to_string(),
-                language_ietf: "fr".to_string(),
-            },
-            MatroskaTrack {
-                id: 3,
-                name: Some("Track 3".to_string()),
-                type_: MatroskaTrackType::Subtitles,
-                default: false,
-                language: "ger".to_string(),
-                language_ietf: "de".to_string(),
-            },
+    fn test_plan_original() {
+        let track = |id: usize, language: &str, original: bool| MatroskaTrack {
+            id,
+            number: Some(id as u64 + 1),
+            original,
+            type_: MatroskaTrackType::Audio,
+            language: language.to_string(),
+            language_ietf: "und".to_string(),
+            ..Default::default()
+        };
+        let tracks = [
+            track(1, "jpn", false),
+            track(2, "eng", true),
+            track(3, "jpn", true),
         ];
-        let tracks: Vec<&MatroskaTrack> = tracks.iter().collect();
 
-        let language_ietf = "fr";
-        let name = Some("Track 2".to_string());
-        let args = generate_args_by_language_ietf(tracks, language_ietf, name);
-        let expected_args = " --edit track:2 --set flag-default=0 --edit track:3 --set flag-default=1 --edit track:4 --set flag-default=0";
+        let args: String = plan_original(tracks.iter().collect(), "ja")
+            .iter()
+            .map(|edit| edit.to_args())
+            .collect::<TempResult<String>>()
+            .unwrap();
+        let expected_args =
+            " --edit track:2 --set flag-original=1 --edit track:3 --set flag-original=0";
         assert_eq!(args, expected_args);
     }
 
     #[test]
-    fn test_generate_args_by_language() {
-        let tracks = vec![
-            MatroskaTrack {
-                id: 1,
-                name: Some("Track 1".to_string()),
-                type_: MatroskaTrackType::Audio,
-                default: false,
-                language: "eng".to_string(),
-                language_ietf: "en".to_string(),
-            },
-            MatroskaTrack {
-                id: 2,
-                name: Some("Track 2".to_string()),
-                type_: MatroskaTrackType::Video,
-                default: false,
-                language: "fre".to_string(),
-                language_ietf: "fr".to_string(),
+    fn test_find_common() {
+        let track = |language_ietf: &str, name: &str| MatroskaTrack {
+            id: 1,
+            name: Some(name.to_string()),
+            type_: MatroskaTrackType::Audio,
+            language: "eng".to_string(),
+            language_ietf: language_ietf.to_string(),
+            ..Default::default()
+        };
+        let mkvs = [
+            Matroska {
+                path: "01.mkv".to_string(),
+                tracks: vec![track("en-US", "Main")],
             },
-            MatroskaTrack {
-                id: 3,
-                name: Some("Track 3".to_string()),
-                type_: MatroskaTrackType::Subtitles,
-                default: false,
-                language: "ger".to_string(),
-                language_ietf: "de".to_string(),
+            Matroska {
+                path: "02.mkv".to_string(),
+                tracks: vec![track("en-GB", "Main"), track("ja", "Main")],
             },
         ];
+        let mkvs: Vec<&Matroska> = mkvs.iter().collect();
+        let preference = |language: &str| {
+            vec![Preference {
+                language: language.to_string(),
+                ..Default::default()
+            }]
+        };
+
+        let english = preference("en");
+        let found = find_common(&mkvs, MatroskaTrackType::Audio, &english);
+        assert!(matches!(found, Ok(Some(Selector::Preferences(_)))));
+
+        let found = find_common(&mkvs, MatroskaTrackType::Audio, &[]);
+        assert!(matches!(found, Ok(None)));
+
+        let british = preference("en-GB");
+        let found = find_common(&mkvs, MatroskaTrackType::Audio, &british);
+        assert!(matches!(found, Err(TempError::TrackNotFound(_, _))));
+    }
+
+    #[test]
+    fn test_plan_matched() {
+        let tracks = [MatroskaTrack {
+            id: 1,
+            type_: MatroskaTrackType::Audio,
+            default: true,
+            language: "eng".to_string(),
+            language_ietf: "en".to_string(),
+            ..Default::default()
+        }];
         let tracks: Vec<&MatroskaTrack> = tracks.iter().collect();
 
-        let language = "eng";
-        let name = Some("Track 1".to_string());
-        let args = generate_args_by_language(tracks, language, name);
-        let expected_args = " --edit track:2 --set flag-default=1 --edit track:3 --set flag-default=0 --edit track:4 --set flag-default=0";
-        assert_eq!(args, expected_args);
+        let japanese = Same::new("jpn", "ja", None);
+        assert!(plan_matched(plan_by_same(tracks.clone(), &japanese)).is_empty());
+        let english = Same::new("eng", "en", None);
+        assert_eq!(plan_matched(plan_by_same(tracks, &english)).len(), 1);
+    }
+
+    #[test]
+    fn test_plan_by_same_commentary() {
+        let track = |id: usize, commentary: bool| MatroskaTrack {
+            id,
+            commentary,
+            type_: MatroskaTrackType::Audio,
+            language: "eng".to_string(),
+            language_ietf: "en".to_string(),
+            ..Default::default()
+        };
+        let tracks = [track(1, false), track(2, true)];
+
+        let edits = plan_by_same(tracks.iter().collect(), &Same::new("eng", "en", None));
+        assert_eq!(edits[0].default, Some(true));
+        assert_eq!(edits[1].default, Some(false));
+    }
+
+    #[test]
+    fn test_plan_by_same_first() {
+        let track = |id: usize| MatroskaTrack {
+            id,
+            type_: MatroskaTrackType::Audio,
+            language: "eng".to_string(),
+            language_ietf: "en".to_string(),
+            ..Default::default()
+        };
+        let tracks = [track(1), track(2)];
+
+        let edits = plan_by_same(tracks.iter().collect(), &Same::new("eng", "en", None));
+        assert_eq!(edits[0].default, Some(true));
+        assert_eq!(edits[1].default, Some(false));
+    }
+
+    #[test]
+    fn test_run_tool_not_found() {
+        let result = run_tool(&mut Command::new("mkv_default_track_missing_tool"));
+        assert!(matches!(result, Err(TempError::ToolNotFound(_))));
+        assert_eq!(result.unwrap_err().outcome(), Outcome::Environment);
     }
 
     #[test]
@@ -395,6 +968,8 @@ mod tests {
     }
 
     #[test]
+    // The fixture of the original test is kept as a vector
+    #[allow(clippy::useless_vec)]
     fn test_get_tracks_languages_ieft() {
         let tracks = vec![
             MatroskaTrack {
@@ -404,6 +979,7 @@ mod tests {
                 default: false,
                 language: "eng".to_string(),
                 language_ietf: "en".to_string(),
+                ..Default::default()
             },
             MatroskaTrack {
                 id: 2,
@@ -412,6 +988,7 @@ mod tests {
                 default: false,
                 language: "fre".to_string(),
                 language_ietf: "fr".to_string(),
+                ..Default::default()
             },
             MatroskaTrack {
                 id: 3,
@@ -420,6 +997,7 @@ mod tests {
                 default: false,
                 language: "ger".to_string(),
                 language_ietf: "und".to_string(),
+                ..Default::default()
             },
         ];
         let tracks: Vec<&MatroskaTrack> = tracks.iter().collect();
@@ -430,17 +1008,27 @@ mod tests {
                 language: "eng".to_owned(),
                 language_ietf: "en".to_owned(),
                 name: Some("Track 1".to_string()),
+                codec: None,
+                channels: None,
+                dimensions: None,
+                commentary: false,
             },
             Same {
                 language: "fre".to_owned(),
                 language_ietf: "fr".to_owned(),
                 name: Some("Track 2".to_string()),
+                codec: None,
+                channels: None,
+                dimensions: None,
+                commentary: false,
             },
         ];
         assert_eq!(result, expected);
     }
 
     #[test]
+    // The fixture of the original test is kept as a vector
+    #[allow(clippy::useless_vec)]
     fn test_get_tracks_languages() {
         let tracks = vec![
             MatroskaTrack {
@@ -450,6 +1038,7 @@ mod tests {
                 default: false,
                 language: "eng".to_string(),
                 language_ietf: "en".to_string(),
+                ..Default::default()
             },
             MatroskaTrack {
                 id: 2,
@@ -458,6 +1047,7 @@ mod tests {
                 default: false,
                 language: "fre".to_string(),
                 language_ietf: "fr".to_string(),
+                ..Default::default()
             },
             MatroskaTrack {
                 id: 3,
@@ -466,6 +1056,7 @@ mod tests {
                 default: false,
                 language: "und".to_string(),
                 language_ietf: "ge".to_string(),
+                ..Default::default()
             },
         ];
         let tracks: Vec<&MatroskaTrack> = tracks.iter().collect();
@@ -476,173 +1067,209 @@ mod tests {
                 language: "eng".to_owned(),
                 language_ietf: "en".to_owned(),
                 name: Some("Track 1".to_string()),
+                codec: None,
+                channels: None,
+                dimensions: None,
+                commentary: false,
             },
             Same {
                 language: "fre".to_owned(),
                 language_ietf: "fr".to_owned(),
                 name: Some("Track 2".to_string()),
+                codec: None,
+                channels: None,
+                dimensions: None,
+                commentary: false,
             },
         ];
         assert_eq!(result, expected);
     }
 
     #[test]
-    fn test_get_same_languages_ietf() {
-        let mkvs = vec![
+    fn test_get_coverage_equivalent_codes() {
+        let track = |language: &str, language_ietf: &str| MatroskaTrack {
+            id: 1,
+            type_: MatroskaTrackType::Audio,
+            language: language.to_string(),
+            language_ietf: language_ietf.to_string(),
+            ..Default::default()
+        };
+        let mkvs = [
             Matroska {
-                path: "test1.mkv".to_string(),
-                tracks: vec![
-                    MatroskaTrack {
-                        id: 1,
-                        name: Some("Track 1".to_string()),
-                        type_: MatroskaTrackType::Audio,
-                        default: false,
-                        language: "eng".to_string(),
-                        language_ietf: "en".to_string(),
-                    },
-                    MatroskaTrack {
-                        id: 2,
-                        name: None,
-                        type_: MatroskaTrackType::Audio,
-                        default: false,
-                        language: "fre".to_string(),
-                        language_ietf: "fr".to_string(),
-                    },
-                    MatroskaTrack {
-                        id: 3,
-                        name: Some("Track 3".to_string()),
-                        type_: MatroskaTrackType::Subtitles,
-                        default: false,
-                        language: "ger".to_string(),
-                        language_ietf: "de".to_string(),
-                    },
-                ],
+                path: "01.mkv".to_string(),
+                tracks: vec![track("fre", "und"), track("ger", "und")],
             },
             Matroska {
-                path: "test2.mkv".to_string(),
-                tracks: vec![
-                    MatroskaTrack {
-                        id: 1,
-                        name: Some("Track 1".to_string()),
-                        type_: MatroskaTrackType::Audio,
-                        default: false,
-                        language: "eng".to_string(),
-                        language_ietf: "en".to_string(),
-                    },
-                    MatroskaTrack {
-                        id: 2,
-                        name: None,
-                        type_: MatroskaTrackType::Audio,
-                        default: false,
-                        language: "fre".to_string(),
-                        language_ietf: "fr".to_string(),
-                    },
-                    MatroskaTrack {
-                        id: 3,
-                        name: Some("Track 3".to_string()),
-                        type_: MatroskaTrackType::Subtitles,
-                        default: false,
-                        language: "spa".to_string(),
-                        language_ietf: "es".to_string(),
-                    },
-                ],
+                path: "02.mkv".to_string(),
+                tracks: vec![track("fra", "fr"), track("deu", "de")],
             },
-        ];
-
-        let track_type = MatroskaTrackType::Audio;
-        let result = get_same_languages_ietf(&mkvs, track_type);
-        let expected = vec![
-            Same {
-                language: "eng".to_string(),
-                language_ietf: "en".to_string(),
-                name: Some("Track 1".to_string()),
-            },
-            Same {
-                language: "fre".to_string(),
-                language_ietf: "fr".to_string(),
-                name: None,
+            Matroska {
+                path: "03.mkv".to_string(),
+                tracks: vec![track("und", "fr"), track("und", "de")],
             },
         ];
-        assert_eq!(result, expected);
+        let mkvs: Vec<&Matroska> = mkvs.iter().collect();
+
+        let coverage = get_coverage(&mkvs, MatroskaTrackType::Audio);
+        let coverage: Vec<String> = coverage.iter().map(|c| c.to_string()).collect();
+        assert_eq!(coverage, vec!["fr — 3/3 files", "de — 3/3 files"]);
+
+        let edits = plan_by_same(mkvs[2].get_audios(), &Same::new("fre", "und", None));
+        assert_eq!(edits[0].default, Some(true));
+        assert_eq!(edits[1].default, Some(false));
     }
 
     #[test]
-    fn test_get_same_languages() {
-        let mkvs = vec![
+    fn test_get_coverage_codecs() {
+        let track = |id: usize, codec: &str, channels: u64| MatroskaTrack {
+            id,
+            codec: Some(codec.to_string()),
+            audio_channels: Some(channels),
+            type_: MatroskaTrackType::Audio,
+            language: "eng".to_string(),
+            language_ietf: "en".to_string(),
+            ..Default::default()
+        };
+        let mkvs = [
             Matroska {
-                path: "test1.mkv".to_string(),
-                tracks: vec![
-                    MatroskaTrack {
-                        id: 1,
-                        name: Some("Track 1".to_string()),
-                        type_: MatroskaTrackType::Audio,
-                        default: false,
-                        language: "eng".to_string(),
-                        language_ietf: "en".to_string(),
-                    },
-                    MatroskaTrack {
-                        id: 2,
-                        name: None,
-                        type_: MatroskaTrackType::Audio,
-                        default: false,
-                        language: "fre".to_string(),
-                        language_ietf: "fr".to_string(),
-                    },
-                    MatroskaTrack {
-                        id: 3,
-                        name: Some("Track 3".to_string()),
-                        type_: MatroskaTrackType::Subtitles,
-                        default: false,
-                        language: "ger".to_string(),
-                        language_ietf: "de".to_string(),
-                    },
-                ],
+                path: "01.mkv".to_string(),
+                tracks: vec![track(1, "AC-3", 6)],
             },
             Matroska {
-                path: "test2.mkv".to_string(),
-                tracks: vec![
-                    MatroskaTrack {
-                        id: 1,
-                        name: Some("Track 1".to_string()),
-                        type_: MatroskaTrackType::Audio,
-                        default: false,
-                        language: "eng".to_string(),
-                        language_ietf: "en".to_string(),
-                    },
-                    MatroskaTrack {
-                        id: 2,
-                        name: None,
-                        type_: MatroskaTrackType::Audio,
-                        default: false,
-                        language: "fre".to_string(),
-                        language_ietf: "fr".to_string(),
-                    },
-                    MatroskaTrack {
-                        id: 3,
-                        name: Some("Track 3".to_string()),
-                        type_: MatroskaTrackType::Subtitles,
-                        default: false,
-                        language: "spa".to_string(),
-                        language_ietf: "es".to_string(),
-                    },
-                ],
+                path: "02.mkv".to_string(),
+                tracks: vec![track(1, "AAC", 2), track(2, "AC-3", 6)],
             },
         ];
+        let mkvs: Vec<&Matroska> = mkvs.iter().collect();
 
-        let track_type = MatroskaTrackType::Audio;
-        let result = get_same_languages(&mkvs, track_type);
-        let expected = vec![
-            Same {
-                language: "eng".to_string(),
-                language_ietf: "en".to_string(),
-                name: Some("Track 1".to_string()),
+        let coverage = get_coverage(&mkvs, MatroskaTrackType::Audio);
+        let coverage: Vec<String> = coverage.iter().map(|c| c.to_string()).collect();
+        assert_eq!(
+            coverage,
+            vec!["en [AC-3, 5.1] — 2/2 files", "en [AAC, stereo] — 1/2 files"]
+        );
+
+        let same = Same::from_track(&mkvs[0].tracks[0]);
+        let edits = plan_by_same(mkvs[1].get_audios(), &same);
+        assert_eq!(edits[0].default, Some(false));
+        assert_eq!(edits[1].default, Some(true));
+
+        let same = Same::from_track(&mkvs[1].tracks[0]);
+        let edits = plan_by_same(mkvs[1].get_audios(), &same);
+        assert_eq!(edits[0].default, Some(true));
+        assert_eq!(edits[1].default, Some(false));
+
+        // The files without the AAC track fall back to the closest english track
+        assert_eq!(
+            plan_by_same(mkvs[0].get_audios(), &same)[0].default,
+            Some(false)
+        );
+        let edits = Selector::Same(vec![same]).plan(mkvs[0].get_audios());
+        assert_eq!(edits[0].default, Some(true));
+    }
+
+    #[test]
+    fn test_get_coverage_channels() {
+        let track = |id: usize, channels: u64| MatroskaTrack {
+            id,
+            codec: Some("FLAC".to_string()),
+            audio_channels: Some(channels),
+            name: Some("Main".to_string()),
+            type_: MatroskaTrackType::Audio,
+            language: "jpn".to_string(),
+            language_ietf: "ja".to_string(),
+            ..Default::default()
+        };
+        let mkvs = [Matroska {
+            path: "01.mkv".to_string(),
+            tracks: vec![track(1, 6), track(2, 2)],
+        }];
+        let mkvs: Vec<&Matroska> = mkvs.iter().collect();
+
+        let coverage = get_coverage(&mkvs, MatroskaTrackType::Audio);
+        let coverage: Vec<String> = coverage.iter().map(|c| c.to_string()).collect();
+        assert_eq!(
+            coverage,
+            vec![
+                "ja (Main) [FLAC, 5.1] — 1/1 files",
+                "ja (Main) [FLAC, stereo] — 1/1 files"
+            ]
+        );
+
+        let same = Same::from_track(&mkvs[0].tracks[1]);
+        let edits = plan_by_same(mkvs[0].get_audios(), &same);
+        assert_eq!(edits[0].default, Some(false));
+        assert_eq!(edits[1].default, Some(true));
+    }
+
+    #[test]
+    fn test_get_coverage_videos() {
+        let track = |id: usize, codec: &str, dimensions: &str| MatroskaTrack {
+            id,
+            codec: Some(codec.to_string()),
+            video_pixel_dimensions: Some(dimensions.to_string()),
+            type_: MatroskaTrackType::Video,
+            language: "und".to_string(),
+            language_ietf: "und".to_string(),
+            ..Default::default()
+        };
+        let mkvs = [Matroska {
+            path: "01.mkv".to_string(),
+            tracks: vec![track(1, "AVC", "720x480"), track(2, "HEVC", "3840x2160")],
+        }];
+        let mkvs: Vec<&Matroska> = mkvs.iter().collect();
+
+        let coverage = get_coverage(&mkvs, MatroskaTrackType::Video);
+        let coverage: Vec<String> = coverage.iter().map(|c| c.to_string()).collect();
+        assert_eq!(
+            coverage,
+            vec![
+                "und [undetermined] [AVC, 720x480] — 1/1 files",
+                "und [undetermined] [HEVC, 3840x2160] — 1/1 files"
+            ]
+        );
+
+        let same = Same::from_track(&mkvs[0].tracks[1]);
+        let edits = plan_by_same(mkvs[0].get_videos(), &same);
+        assert_eq!(edits[0].default, Some(false));
+        assert_eq!(edits[1].default, Some(true));
+    }
+
+    #[test]
+    fn test_get_coverage() {
+        let track = |id: usize, language: &str, name: &str| MatroskaTrack {
+            id,
+            name: Some(name.to_string()),
+            type_: MatroskaTrackType::Audio,
+            language: language.to_string(),
+            language_ietf: "und".to_string(),
+            ..Default::default()
+        };
+        let mkvs = [
+            Matroska {
+                path: "01.mkv".to_string(),
+                tracks: vec![track(1, "jpn", "Main"), track(2, "eng", "Dub")],
             },
-            Same {
-                language: "fre".to_string(),
-                language_ietf: "fr".to_string(),
-                name: None,
+            Matroska {
+                path: "02.mkv".to_string(),
+                tracks: vec![track(1, "jpn", "Main")],
+            },
+            Matroska {
+                path: "03.mkv".to_string(),
+                tracks: vec![track(1, "jpn", "Main (Remastered)")],
             },
         ];
-        assert_eq!(result, expected);
+        let mkvs: Vec<&Matroska> = mkvs.iter().collect();
+
+        let coverage = get_coverage(&mkvs, MatroskaTrackType::Audio);
+        let coverage: Vec<String> = coverage.iter().map(|c| c.to_string()).collect();
+        assert_eq!(
+            coverage,
+            vec![
+                "ja (Main) — 2/3 files",
+                "en (Dub) — 1/3 files",
+                "ja (Main (Remastered)) — 1/3 files",
+            ]
+        );
     }
 }
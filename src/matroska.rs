@@ -1,8 +1,14 @@
+use std::fmt;
+use std::path::PathBuf;
+
 use crate::{
     deserialize::*,
     error::{TempError, TempResult},
 };
 
+/// Words of the track names that mark a commentary, matched case-insensitively
+const COMMENTARY_NAMES: &[&str] = &["commentary", "director's", "directors"];
+
 #[derive(Debug)]
 pub struct Matroska {
     pub path: String,
@@ -12,21 +18,104 @@ pub struct Matroska {
 #[derive(Debug)]
 pub struct MatroskaTrack {
     pub id: usize,
+    pub number: Option<u64>,
+    pub uid: Option<u64>,
+    /// Name of the codec given by mkvmerge (e.g. 'FLAC' or 'HDMV PGS')
+    pub codec: Option<String>,
+    /// Matroska codec id (e.g. 'A_FLAC' or 'S_HDMV/PGS')
+    pub codec_id: Option<String>,
+    pub audio_channels: Option<u64>,
+    /// Sampling frequency of the audio in Hz
+    pub audio_sampling_frequency: Option<u64>,
+    pub audio_bits_per_sample: Option<u64>,
+    /// Width and height of the video (e.g. '1920x1080')
+    pub video_pixel_dimensions: Option<String>,
+    /// Commentary flag of the track, see 'is_commentary' for the name heuristic
+    pub commentary: bool,
+    pub hearing_impaired: bool,
+    pub visual_impaired: bool,
+    pub text_descriptions: bool,
+    /// Is the track in the original language of the content
+    pub original: bool,
     pub name: Option<String>,
     pub type_: MatroskaTrackType,
     pub default: bool,
+    pub forced: bool,
     pub language: String,
     pub language_ietf: String,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum MatroskaTrackType {
     Audio,
     Video,
     Subtitles,
+    /// Track type unknown to this tool (e.g. buttons), never selected
+    Other(String),
+}
+
+impl fmt::Display for MatroskaTrackType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self {
+            MatroskaTrackType::Audio => write!(f, "audio"),
+            MatroskaTrackType::Video => write!(f, "video"),
+            MatroskaTrackType::Subtitles => write!(f, "subtitle"),
+            MatroskaTrackType::Other(type_) => write!(f, "{type_}"),
+        }
+    }
+}
+
+/// Track of an unknown type without any property or flag, its language is undetermined
+impl Default for MatroskaTrack {
+    fn default() -> Self {
+        Self {
+            id: 0,
+            number: None,
+            uid: None,
+            codec: None,
+            codec_id: None,
+            audio_channels: None,
+            audio_sampling_frequency: None,
+            audio_bits_per_sample: None,
+            video_pixel_dimensions: None,
+            commentary: false,
+            hearing_impaired: false,
+            visual_impaired: false,
+            text_descriptions: false,
+            original: false,
+            name: None,
+            type_: MatroskaTrackType::Other(String::new()),
+            default: false,
+            forced: false,
+            language: "und".to_string(),
+            language_ietf: "und".to_string(),
+        }
+    }
 }
 
 impl MatroskaTrack {
+    /// Get the mkvpropedit selector of the track, by uid or else by track number,
+    /// the track id of mkvmerge is not a reliable track number
+    pub fn selector(&self) -> TempResult<String> {
+        match (self.uid, self.number) {
+            (Some(uid), _) => Ok(format!("track:={uid}")),
+            (None, Some(number)) => Ok(format!("track:{number}")),
+            (None, None) => Err(TempError::UnselectableTrack(self.id)),
+        }
+    }
+
+    /// Get the height of the video from its pixel dimensions
+    pub fn video_height(&self) -> Option<u64> {
+        let (_, height) = self.video_pixel_dimensions.as_deref()?.split_once('x')?;
+        height.parse().ok()
+    }
+
+    /// Is this a commentary, by its flag or by its name
+    pub fn is_commentary(&self) -> bool {
+        let name = self.name.as_deref().unwrap_or_default().to_lowercase();
+        self.commentary || COMMENTARY_NAMES.iter().any(|word| name.contains(word))
+    }
+
     /// Is this a video
     pub fn is_video(&self) -> bool {
         matches!(self.type_, MatroskaTrackType::Video)
@@ -49,6 +138,10 @@ impl Matroska {
         let infos: DeserializeMatroska =
             serde_json::from_str(&input).map_err(TempError::Deserialize)?;
 
+        if matches!(infos.container, Some(container) if !container.recognized) {
+            return Err(TempError::UnrecognizedFile(PathBuf::from(path)));
+        }
+
         Ok(Self {
             path: path.to_owned(),
             tracks: infos
@@ -56,11 +149,27 @@ impl Matroska {
                 .iter()
                 .map(|track| MatroskaTrack {
                     id: track.id,
+                    number: track.properties.number,
+                    uid: track.properties.uid,
+                    codec: track.codec.clone(),
+                    codec_id: track.properties.codec_id.clone(),
+                    audio_channels: track.properties.audio_channels,
+                    audio_sampling_frequency: track.properties.audio_sampling_frequency,
+                    audio_bits_per_sample: track.properties.audio_bits_per_sample,
+                    video_pixel_dimensions: track.properties.video_pixel_dimensions.clone(),
+                    commentary: track.properties.flag_commentary,
+                    hearing_impaired: track.properties.flag_hearing_impaired,
+                    visual_impaired: track.properties.flag_visual_impaired,
+                    text_descriptions: track.properties.flag_text_descriptions,
+                    original: track.properties.flag_original,
                     name: track.properties.track_name.clone(),
-                    type_: match track.type_ {
+                    type_: match &track.type_ {
                         DeserializeMatroskaTrackType::Audio => MatroskaTrackType::Audio,
                         DeserializeMatroskaTrackType::Video => MatroskaTrackType::Video,
                         DeserializeMatroskaTrackType::Subtitles => MatroskaTrackType::Subtitles,
+                        DeserializeMatroskaTrackType::Other(type_) => {
+                            MatroskaTrackType::Other(type_.to_owned())
+                        }
                     },
                     language: track.properties.language.clone(),
                     language_ietf: track
@@ -69,11 +178,26 @@ impl Matroska {
                         .clone()
                         .unwrap_or_else(|| "und".to_string()),
                     default: track.properties.default_track,
+                    forced: track.properties.forced_track,
                 })
                 .collect(),
         })
     }
 
+    /// Get the tracks of a type
+    pub fn get_tracks(&self, track_type: &MatroskaTrackType) -> Vec<&MatroskaTrack> {
+        match track_type {
+            MatroskaTrackType::Audio => self.get_audios(),
+            MatroskaTrackType::Video => self.get_videos(),
+            MatroskaTrackType::Subtitles => self.get_subtitles(),
+            MatroskaTrackType::Other(type_) => self
+                .tracks
+                .iter()
+                .filter(|t| matches!(&t.type_, MatroskaTrackType::Other(other) if other == type_))
+                .collect(),
+        }
+    }
+
     /// Get videos tracks
     pub fn get_videos(&self) -> Vec<&MatroskaTrack> {
         self.tracks.iter().filter(|t| t.is_video()).collect()
@@ -112,7 +236,9 @@ mod tests {
             {
                 "id": 2,
                 "type": "video",
+                "codec": "HEVC/H.265/MPEG-H",
                 "properties": {
+                    "video_pixel_dimensions": "3840x2160",
                     "track_name": "Track 2",
                     "language": "fre",
                     "language_ietf": "fr",
@@ -134,6 +260,137 @@ mod tests {
         assert_eq!(matroska.tracks[1].language, "fre");
         assert_eq!(matroska.tracks[1].language_ietf, "fr");
         assert!(!matroska.tracks[1].default);
+        assert_eq!(matroska.tracks[1].video_height(), Some(2160));
+        assert_eq!(matroska.tracks[0].video_height(), None);
+        assert_eq!(matroska.tracks[0].number, None);
+        assert_eq!(matroska.tracks[0].uid, None);
+        assert!(!matroska.tracks[0].forced);
+    }
+
+    #[test]
+    fn test_matroska_from_string_unknown_type() {
+        let input = r#"{
+        "tracks": [
+            {
+                "id": 0,
+                "type": "buttons",
+                "properties": {
+                    "default_track": false
+                }
+            },
+            {
+                "id": 1,
+                "type": "audio",
+                "properties": {
+                    "language": "jpn",
+                    "default_track": true
+                }
+            }
+        ]
+    }"#
+        .to_string();
+        let matroska = Matroska::from_string("test.mkv", input).unwrap();
+        assert!(
+            matches!(&matroska.tracks[0].type_, MatroskaTrackType::Other(type_) if type_ == "buttons")
+        );
+        assert_eq!(matroska.tracks[0].type_.to_string(), "buttons");
+        assert_eq!(matroska.tracks[0].language, "und");
+        assert!(!matroska.tracks[0].is_audio());
+        assert!(!matroska.tracks[0].is_subtitle());
+        assert_eq!(matroska.get_audios().len(), 1);
+    }
+
+    #[test]
+    fn test_matroska_from_string_number_uid() {
+        let input = r#"{
+        "tracks": [
+            {
+                "id": 0,
+                "type": "audio",
+                "codec": "FLAC",
+                "properties": {
+                    "number": 3,
+                    "codec_id": "A_FLAC",
+                    "audio_channels": 6,
+                    "audio_sampling_frequency": 48000,
+                    "audio_bits_per_sample": 24,
+                    "uid": 18446744073709551615,
+                    "language": "jpn",
+                    "default_track": true,
+                    "forced_track": true,
+                    "flag_commentary": true,
+                    "flag_visual_impaired": true,
+                    "flag_original": true
+                }
+            }
+        ]
+    }"#
+        .to_string();
+        let matroska = Matroska::from_string("test.mkv", input).unwrap();
+        assert!(matroska.tracks[0].forced);
+        assert!(matroska.tracks[0].commentary);
+        assert!(matroska.tracks[0].visual_impaired);
+        assert!(!matroska.tracks[0].hearing_impaired);
+        assert!(matroska.tracks[0].original);
+        assert_eq!(matroska.tracks[0].number, Some(3));
+        assert_eq!(matroska.tracks[0].uid, Some(u64::MAX));
+        assert_eq!(matroska.tracks[0].codec.as_deref(), Some("FLAC"));
+        assert_eq!(matroska.tracks[0].codec_id.as_deref(), Some("A_FLAC"));
+        assert_eq!(matroska.tracks[0].audio_channels, Some(6));
+        assert_eq!(matroska.tracks[0].audio_sampling_frequency, Some(48000));
+        assert_eq!(matroska.tracks[0].audio_bits_per_sample, Some(24));
+    }
+
+    #[test]
+    fn test_matroska_track_is_commentary() {
+        let mut track = MatroskaTrack {
+            id: 1,
+            name: Some("Main".to_string()),
+            type_: MatroskaTrackType::Audio,
+            language: "eng".to_string(),
+            language_ietf: "en".to_string(),
+            ..Default::default()
+        };
+        assert!(!track.is_commentary());
+
+        track.name = Some("Director's Cut Commentary".to_string());
+        assert!(track.is_commentary());
+
+        track.name = None;
+        track.commentary = true;
+        assert!(track.is_commentary());
+    }
+
+    #[test]
+    fn test_matroska_track_selector() {
+        let mut track = MatroskaTrack {
+            id: 1,
+            type_: MatroskaTrackType::Audio,
+            language: "".to_string(),
+            language_ietf: "".to_string(),
+            ..Default::default()
+        };
+        assert!(matches!(
+            track.selector(),
+            Err(TempError::UnselectableTrack(1))
+        ));
+
+        track.number = Some(4);
+        assert_eq!(track.selector().unwrap(), "track:4");
+
+        track.uid = Some(123456789);
+        assert_eq!(track.selector().unwrap(), "track:=123456789");
+    }
+
+    #[test]
+    fn test_matroska_from_string_unrecognized() {
+        let input = r#"{
+        "container": { "recognized": false, "supported": false },
+        "errors": ["The file could not be opened for reading."]
+    }"#
+        .to_string();
+        let result = Matroska::from_string("test.mkv", input);
+        assert!(matches!(result, Err(TempError::UnrecognizedFile(_))));
     }
 
     #[test]
@@ -145,6 +402,7 @@ mod tests {
             default: false,
             language: "".to_string(),
             language_ietf: "".to_string(),
+            ..Default::default()
         };
         assert!(track.is_video());
     }
@@ -158,6 +416,7 @@ mod tests {
             default: false,
             language: "".to_string(),
             language_ietf: "".to_string(),
+            ..Default::default()
         };
         assert!(track.is_audio());
     }
@@ -171,6 +430,7 @@ mod tests {
             default: false,
             language: "".to_string(),
             language_ietf: "".to_string(),
+            ..Default::default()
         };
         assert!(track.is_subtitle());
     }
@@ -187,6 +447,7 @@ mod tests {
                     default: false,
                     language: "".to_string(),
                     language_ietf: "".to_string(),
+                    ..Default::default()
                 },
                 MatroskaTrack {
                     id: 2,
@@ -195,6 +456,7 @@ mod tests {
                     default: false,
                     language: "".to_string(),
                     language_ietf: "".to_string(),
+                    ..Default::default()
                 },
                 MatroskaTrack {
                     id: 3,
@@ -203,6 +465,7 @@ mod tests {
                     default: false,
                     language: "".to_string(),
                     language_ietf: "".to_string(),
+                    ..Default::default()
                 },
             ],
         };
@@ -224,6 +487,7 @@ mod tests {
                     default: false,
                     language: "".to_string(),
                     language_ietf: "".to_string(),
+                    ..Default::default()
                 },
                 MatroskaTrack {
                     id: 2,
@@ -232,6 +496,7 @@ mod tests {
                     default: false,
                     language: "".to_string(),
                     language_ietf: "".to_string(),
+                    ..Default::default()
                 },
                 MatroskaTrack {
                     id: 3,
@@ -240,6 +505,7 @@ mod tests {
                     default: false,
                     language: "".to_string(),
                     language_ietf: "".to_string(),
+                    ..Default::default()
                 },
             ],
         };
@@ -261,6 +527,7 @@ mod tests {
                     default: false,
                     language: "".to_string(),
                     language_ietf: "".to_string(),
+                    ..Default::default()
                 },
                 MatroskaTrack {
                     id: 2,
@@ -269,6 +536,7 @@ mod tests {
                     default: false,
                     language: "".to_string(),
                     language_ietf: "".to_string(),
+                    ..Default::default()
                 },
                 MatroskaTrack {
                     id: 3,
@@ -277,6 +545,7 @@ mod tests {
                     default: false,
                     language: "".to_string(),
                     language_ietf: "".to_string(),
+                    ..Default::default()
                 },
             ],
         };
@@ -325,6 +594,7 @@ mod tests {
             default: false,
             language: "".to_string(),
             language_ietf: "".to_string(),
+            ..Default::default()
         };
         assert!(!track.is_video());
     }
@@ -338,6 +608,7 @@ mod tests {
             default: false,
             language: "".to_string(),
             language_ietf: "".to_string(),
+            ..Default::default()
         };
         assert!(!track.is_audio());
     }
@@ -351,6 +622,7 @@ mod tests {
             default: false,
             language: "".to_string(),
             language_ietf: "".to_string(),
+            ..Default::default()
         };
         assert!(!track.is_subtitle());
     }
@@ -367,6 +639,7 @@ mod tests {
                     default: false,
                     language: "".to_string(),
                     language_ietf: "".to_string(),
+                    ..Default::default()
                 },
                 MatroskaTrack {
                     id: 2,
@@ -375,6 +648,7 @@ mod tests {
                     default: false,
                     language: "".to_string(),
                     language_ietf: "".to_string(),
+                    ..Default::default()
                 },
             ],
         };
@@ -394,6 +668,7 @@ mod tests {
                     default: false,
                     language: "".to_string(),
                     language_ietf: "".to_string(),
+                    ..Default::default()
                 },
                 MatroskaTrack {
                     id: 2,
@@ -402,6 +677,7 @@ mod tests {
                     default: false,
                     language: "".to_string(),
                     language_ietf: "".to_string(),
+                    ..Default::default()
                 },
             ],
         };
@@ -421,6 +697,7 @@ mod tests {
                     default: false,
                     language: "".to_string(),
                     language_ietf: "".to_string(),
+                    ..Default::default()
                 },
                 MatroskaTrack {
                     id: 2,
@@ -429,6 +706,7 @@ mod tests {
                     default: false,
                     language: "".to_string(),
                     language_ietf: "".to_string(),
+                    ..Default::default()
                 },
             ],
         };
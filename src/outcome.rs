@@ -0,0 +1,99 @@
+use crate::ResultCommand;
+
+/// Outcome of a run, used as the exit code of the process
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    /// Every file has been edited
+    Success = 0,
+    /// Unexpected error
+    Error = 1,
+    /// Some files could not be edited
+    PartialFailure = 2,
+    /// No file could be edited
+    TotalFailure = 3,
+    /// No file or no common track to edit
+    NothingToDo = 4,
+    /// mkvtoolnix is not installed
+    Environment = 5,
+    /// Invalid command line, clap's own usage code '2' is taken by 'PartialFailure'
+    Usage = 6,
+}
+
+impl Outcome {
+    /// Get the outcome from the results of the commands
+    pub fn from_results(results: &[ResultCommand]) -> Self {
+        let attempted = results.iter().filter(|result| !result.is_skipped()).count();
+        let failed = results
+            .iter()
+            .filter(|result| result.is_error() || result.is_identify_error())
+            .count();
+
+        match failed {
+            _ if attempted == 0 => Outcome::NothingToDo,
+            0 => Outcome::Success,
+            failed if failed == attempted => Outcome::TotalFailure,
+            _ => Outcome::PartialFailure,
+        }
+    }
+
+    /// Get the exit code of the process
+    pub fn code(self) -> i32 {
+        self as i32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn success() -> ResultCommand {
+        ResultCommand::Success("a.mkv".to_string())
+    }
+
+    fn error() -> ResultCommand {
+        ResultCommand::Error("b.mkv".to_string(), "Error".to_string())
+    }
+
+    #[test]
+    fn test_from_results() {
+        assert_eq!(Outcome::from_results(&[]), Outcome::NothingToDo);
+        assert_eq!(Outcome::from_results(&[success()]), Outcome::Success);
+        assert_eq!(
+            Outcome::from_results(&[success(), error()]),
+            Outcome::PartialFailure
+        );
+        assert_eq!(
+            Outcome::from_results(&[error(), error()]),
+            Outcome::TotalFailure
+        );
+    }
+
+    #[test]
+    fn test_from_results_skipped_and_identify_errors() {
+        let skipped = || ResultCommand::Skipped("c.mkv".to_string());
+        let identify_error =
+            || ResultCommand::IdentifyError("d.mkv".to_string(), "Error".to_string());
+
+        assert_eq!(Outcome::from_results(&[skipped()]), Outcome::NothingToDo);
+        assert_eq!(
+            Outcome::from_results(&[success(), skipped()]),
+            Outcome::Success
+        );
+        assert_eq!(
+            Outcome::from_results(&[success(), identify_error()]),
+            Outcome::PartialFailure
+        );
+        assert_eq!(
+            Outcome::from_results(&[identify_error(), skipped()]),
+            Outcome::TotalFailure
+        );
+    }
+
+    #[test]
+    fn test_code() {
+        assert_eq!(Outcome::Success.code(), 0);
+        assert_eq!(Outcome::PartialFailure.code(), 2);
+        assert_eq!(Outcome::Environment.code(), 5);
+        assert_eq!(Outcome::Usage.code(), 6);
+    }
+}
@@ -0,0 +1,729 @@
+use std::fmt;
+use std::process::Command;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{TempError, TempResult};
+use crate::language;
+use crate::matroska::MatroskaTrack;
+use crate::preference::{resolve, Preference, Resolution};
+use crate::remux::generate_remux_command;
+use crate::same::Same;
+use crate::undetermined::UNDETERMINED;
+use crate::{
+    generate_command, plan_by_same, plan_by_similar, plan_by_track, plan_disabled, plan_matched,
+};
+
+/// How the default track of a type is chosen in each file
+#[derive(Debug, Clone)]
+pub enum Selector<'a> {
+    /// Tracks chosen among the files, the first one present in the file wins,
+    /// then the first one with a track of its language and name
+    Same(Vec<Same>),
+    /// First preference matching the tracks of the file
+    Preferences(&'a [Preference]),
+    /// No track is default
+    Disabled,
+}
+
+/// What to do with the forced flag of the subtitles
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ForcedMode {
+    /// Leave the forced flags untouched
+    Keep,
+    /// Set the forced flag on the chosen track and clear it on the others
+    Set,
+    /// Clear the forced flag on every subtitle, chosen or not
+    Clear,
+}
+
+/// Other flags of a track that can be edited
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flag {
+    HearingImpaired,
+    VisualImpaired,
+    TextDescriptions,
+    Original,
+}
+
+/// Planned flags of a track, 'None' leaves the flag untouched
+#[derive(Debug, Clone)]
+pub struct TrackEdit<'a> {
+    pub track: &'a MatroskaTrack,
+    pub default: Option<bool>,
+    pub forced: Option<bool>,
+    pub flags: Vec<(Flag, bool)>,
+    /// Language and language ietf given to an undetermined track, the track already has them
+    pub language: Option<(String, String)>,
+}
+
+/// How the edits are written to the file
+#[derive(Debug, Clone, Copy)]
+pub enum Backend<'a> {
+    /// Edit the flags in place with mkvpropedit
+    Propedit,
+    /// Rewrite the file with mkvmerge, the default tracks first, 'tracks' are every track of the file
+    Remux(&'a [MatroskaTrack]),
+}
+
+/// Planned edits of a file
+#[derive(Debug)]
+pub struct FilePlan<'a> {
+    pub path: &'a str,
+    pub edits: Vec<TrackEdit<'a>>,
+    pub backend: Backend<'a>,
+    /// Every track of the file, the tracks without an edit are reported untouched
+    pub tracks: &'a [MatroskaTrack],
+}
+
+#[derive(Serialize)]
+struct SerializeFilePlan<'a> {
+    path: &'a str,
+    argv: Vec<String>,
+    tracks: Vec<SerializeTrackEdit<'a>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SerializeTrackEdit<'a> {
+    id: usize,
+    number: Option<u64>,
+    uid: Option<u64>,
+    #[serde(rename = "type")]
+    type_: String,
+    language: &'a str,
+    language_ietf: &'a str,
+    name: Option<&'a str>,
+    codec: Option<&'a str>,
+    audio_channels: Option<u64>,
+    audio_sampling_frequency: Option<u64>,
+    audio_bits_per_sample: Option<u64>,
+    commentary: bool,
+    default: bool,
+    planned_default: bool,
+    forced: bool,
+    planned_forced: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    flags: Vec<SerializeFlagEdit>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    planned_language: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    planned_language_ietf: Option<&'a str>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SerializeFlagEdit {
+    flag: &'static str,
+    value: bool,
+    planned_value: bool,
+}
+
+impl Selector<'_> {
+    /// Get the selector of the preferences, none if there is no preference
+    pub fn from_preferences(preferences: &[Preference]) -> Option<Selector<'_>> {
+        match preferences.is_empty() {
+            true => None,
+            false => Some(Selector::Preferences(preferences)),
+        }
+    }
+
+    /// Plan the default flags of the tracks, empty if no track matches
+    pub fn plan<'t>(&self, tracks: Vec<&'t MatroskaTrack>) -> Vec<TrackEdit<'t>> {
+        match self {
+            Selector::Same(sames) => sames
+                .iter()
+                .map(|same| plan_matched(plan_by_same(tracks.clone(), same)))
+                .chain(
+                    sames
+                        .iter()
+                        .map(|same| plan_matched(plan_by_similar(tracks.clone(), same))),
+                )
+                .find(|edits| !edits.is_empty())
+                .unwrap_or_default(),
+            Selector::Preferences(preferences) => match resolve(preferences, &tracks) {
+                Some(Resolution::Track(chosen)) => plan_by_track(tracks, chosen),
+                Some(Resolution::Disabled) => plan_disabled(tracks),
+                None => vec![],
+            },
+            Selector::Disabled => plan_disabled(tracks),
+        }
+    }
+}
+
+impl Flag {
+    /// Every flag that can be edited
+    pub const ALL: [Flag; 4] = [
+        Flag::HearingImpaired,
+        Flag::VisualImpaired,
+        Flag::TextDescriptions,
+        Flag::Original,
+    ];
+
+    /// Get the name of the flag in the reports
+    pub fn name(&self) -> &'static str {
+        match self {
+            Flag::HearingImpaired => "hearing_impaired",
+            Flag::VisualImpaired => "visual_impaired",
+            Flag::TextDescriptions => "text_descriptions",
+            Flag::Original => "original",
+        }
+    }
+
+    /// Get the mkvpropedit property of the flag
+    pub fn property(&self) -> &'static str {
+        match self {
+            Flag::HearingImpaired => "flag-hearing-impaired",
+            Flag::VisualImpaired => "flag-visual-impaired",
+            Flag::TextDescriptions => "flag-text-descriptions",
+            Flag::Original => "flag-original",
+        }
+    }
+
+    /// Get the mkvmerge option of the flag
+    pub fn option(&self) -> &'static str {
+        match self {
+            Flag::HearingImpaired => "--hearing-impaired-flag",
+            Flag::VisualImpaired => "--visual-impaired-flag",
+            Flag::TextDescriptions => "--text-descriptions-flag",
+            Flag::Original => "--original-flag",
+        }
+    }
+
+    /// Get the current value of the flag on the track
+    pub fn get(&self, track: &MatroskaTrack) -> bool {
+        match self {
+            Flag::HearingImpaired => track.hearing_impaired,
+            Flag::VisualImpaired => track.visual_impaired,
+            Flag::TextDescriptions => track.text_descriptions,
+            Flag::Original => track.original,
+        }
+    }
+}
+
+impl<'a> TrackEdit<'a> {
+    pub fn new(track: &'a MatroskaTrack, default: bool) -> Self {
+        Self {
+            default: Some(default),
+            ..Self::untouched(track)
+        }
+    }
+
+    /// Get an edit leaving every flag of the track untouched
+    pub fn untouched(track: &'a MatroskaTrack) -> Self {
+        Self {
+            track,
+            default: None,
+            forced: None,
+            flags: vec![],
+            language: None,
+        }
+    }
+
+    /// Get the mkvpropedit args of the edit, the track must have a uid or a number
+    pub fn to_args(&self) -> TempResult<String> {
+        let mut args = format!(" --edit {}", self.track.selector()?);
+        if let Some(default) = self.default {
+            args.push_str(&format!(" --set flag-default={}", default as u8));
+        }
+        if let Some(forced) = self.forced {
+            args.push_str(&format!(" --set flag-forced={}", forced as u8));
+        }
+        for (flag, value) in &self.flags {
+            args.push_str(&format!(" --set {}={}", flag.property(), *value as u8));
+        }
+        if let Some((language, language_ietf)) = &self.language {
+            args.push_str(&format!(
+                " --set language={language} --set language-ietf={language_ietf}"
+            ));
+        }
+        Ok(args)
+    }
+}
+
+impl fmt::Display for TrackEdit<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let track = self.track;
+        let language = language::canonical(&track.language, &track.language_ietf);
+        let name = match &track.name {
+            Some(name) => format!("{language} ({name})"),
+            None => language.to_owned(),
+        };
+
+        write!(
+            f,
+            "{:<8} #{:<3} {name:<30}",
+            track.type_.to_string(),
+            track.id,
+        )?;
+        if let Some(default) = self.default {
+            write!(
+                f,
+                " default: {} -> {}",
+                yes_no(track.default),
+                yes_no(default)
+            )?;
+        }
+        if let Some(forced) = self.forced {
+            write!(
+                f,
+                "  forced: {} -> {}",
+                yes_no(track.forced),
+                yes_no(forced)
+            )?;
+        }
+        for (flag, value) in &self.flags {
+            write!(
+                f,
+                "  {}: {} -> {}",
+                flag.name(),
+                yes_no(flag.get(track)),
+                yes_no(*value)
+            )?;
+        }
+        if let Some((_, language_ietf)) = &self.language {
+            write!(f, "  language: {UNDETERMINED} -> {language_ietf}")?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a> FilePlan<'a> {
+    pub fn new(path: &'a str, edits: Vec<TrackEdit<'a>>) -> Self {
+        Self {
+            path,
+            edits,
+            backend: Backend::Propedit,
+            tracks: &[],
+        }
+    }
+
+    /// Get the edit of every track of the file in order, untouched if the track has none
+    pub fn inventory(&self) -> Vec<TrackEdit<'a>> {
+        if self.tracks.is_empty() {
+            return self.edits.clone();
+        }
+        self.tracks
+            .iter()
+            .map(|track| {
+                self.edits
+                    .iter()
+                    .find(|edit| std::ptr::eq(edit.track, track))
+                    .cloned()
+                    .unwrap_or_else(|| TrackEdit::untouched(track))
+            })
+            .collect()
+    }
+
+    /// Get the mkvpropedit args of every edit
+    pub fn args(&self) -> TempResult<String> {
+        self.edits.iter().map(|edit| edit.to_args()).collect()
+    }
+
+    /// Generate the mkvpropedit command, or the mkvmerge command of a remux,
+    /// fails if mkvpropedit can't select one of the tracks
+    pub fn command(&self) -> TempResult<Command> {
+        Ok(match self.backend {
+            Backend::Propedit => generate_command(self.path, &[&self.args()?]),
+            Backend::Remux(tracks) => generate_remux_command(self.path, tracks, &self.edits),
+        })
+    }
+
+    /// Get the program and the args of the command
+    pub fn argv(&self) -> TempResult<Vec<String>> {
+        let command = self.command()?;
+        Ok(std::iter::once(command.get_program())
+            .chain(command.get_args())
+            .map(|arg| arg.to_string_lossy().to_string())
+            .collect())
+    }
+
+    /// Get the command as a shell-quoted line
+    pub fn to_shell(&self) -> TempResult<String> {
+        Ok(self
+            .argv()?
+            .iter()
+            .map(|arg| shell_quote(arg))
+            .collect::<Vec<String>>()
+            .join(" "))
+    }
+
+    /// Get the command and the planned flags of every track, one track per line
+    pub fn to_text(&self) -> TempResult<String> {
+        let mut text = self.to_shell()?;
+        for edit in &self.inventory() {
+            text.push_str(&format!("\n    {edit}"));
+        }
+        Ok(text)
+    }
+
+    /// Get the plan as a json line
+    pub fn to_json(&self) -> TempResult<String> {
+        let plan = SerializeFilePlan {
+            path: self.path,
+            argv: self.argv()?,
+            tracks: self.serialize_tracks(),
+        };
+        serde_json::to_string(&plan).map_err(TempError::Serialize)
+    }
+
+    /// Get the current and the planned flags of every track of the file,
+    /// a tagged track was undetermined before its language was given
+    pub fn serialize_tracks(&self) -> Vec<SerializeTrackEdit<'a>> {
+        self.inventory()
+            .iter()
+            .map(|edit| SerializeTrackEdit {
+                id: edit.track.id,
+                number: edit.track.number,
+                uid: edit.track.uid,
+                type_: edit.track.type_.to_string(),
+                language: match edit.language {
+                    Some(_) => UNDETERMINED,
+                    None => &edit.track.language,
+                },
+                language_ietf: match edit.language {
+                    Some(_) => UNDETERMINED,
+                    None => &edit.track.language_ietf,
+                },
+                name: edit.track.name.as_deref(),
+                codec: edit.track.codec.as_deref(),
+                audio_channels: edit.track.audio_channels,
+                audio_sampling_frequency: edit.track.audio_sampling_frequency,
+                audio_bits_per_sample: edit.track.audio_bits_per_sample,
+                commentary: edit.track.is_commentary(),
+                default: edit.track.default,
+                planned_default: edit.default.unwrap_or(edit.track.default),
+                forced: edit.track.forced,
+                planned_forced: edit.forced.unwrap_or(edit.track.forced),
+                flags: edit
+                    .flags
+                    .iter()
+                    .map(|(flag, value)| SerializeFlagEdit {
+                        flag: flag.name(),
+                        value: flag.get(edit.track),
+                        planned_value: *value,
+                    })
+                    .collect(),
+                planned_language: edit.language.as_ref().map(|_| edit.track.language.as_str()),
+                planned_language_ietf: edit
+                    .language
+                    .as_ref()
+                    .map(|_| edit.track.language_ietf.as_str()),
+            })
+            .collect()
+    }
+}
+
+/// Plan the forced flags of the subtitles from their planned default flags
+pub fn apply_forced(edits: &mut [TrackEdit], mode: ForcedMode) {
+    for edit in edits {
+        edit.forced = match mode {
+            ForcedMode::Keep => None,
+            ForcedMode::Set => edit.default,
+            ForcedMode::Clear => Some(false),
+        };
+    }
+}
+
+/// Plan the forced flags cleared on the tracks that have it, whether or not a default track is chosen
+pub fn plan_unforced<'a>(tracks: Vec<&'a MatroskaTrack>) -> Vec<TrackEdit<'a>> {
+    tracks
+        .into_iter()
+        .filter(|track| track.forced)
+        .map(|track| TrackEdit {
+            forced: Some(false),
+            ..TrackEdit::untouched(track)
+        })
+        .collect()
+}
+
+/// Add the 'others' edits to the edits, merged with the edit of the same track
+pub fn merge_edits<'a>(edits: &mut Vec<TrackEdit<'a>>, others: Vec<TrackEdit<'a>>) {
+    for other in others {
+        match edits
+            .iter_mut()
+            .find(|edit| std::ptr::eq(edit.track, other.track))
+        {
+            Some(edit) => {
+                edit.default = other.default.or(edit.default);
+                edit.forced = other.forced.or(edit.forced);
+                edit.flags.extend(other.flags);
+                edit.language = other.language.or(edit.language.take());
+            }
+            None => edits.push(other),
+        }
+    }
+}
+
+fn yes_no(value: bool) -> &'static str {
+    match value {
+        true => "yes",
+        false => "no",
+    }
+}
+
+/// Quote the argument for a POSIX shell
+pub fn shell_quote(arg: &str) -> String {
+    let is_safe = |c: char| c.is_ascii_alphanumeric() || "_-./:=,+@%".contains(c);
+    if !arg.is_empty() && arg.chars().all(is_safe) {
+        return arg.to_owned();
+    }
+    format!("'{}'", arg.replace('\'', r"'\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matroska::MatroskaTrackType;
+
+    fn track(id: usize, default: bool) -> MatroskaTrack {
+        MatroskaTrack {
+            id,
+            number: Some(id as u64 + 1),
+            name: Some("Full".to_string()),
+            type_: MatroskaTrackType::Subtitles,
+            default,
+            language: "eng".to_string(),
+            language_ietf: "en".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_selector_preferences() {
+        let mut full = track(1, false);
+        full.name = Some("Full".to_string());
+        let mut signs = track(2, true);
+        signs.name = Some("Signs".to_string());
+        let tracks = [signs, full];
+        let tracks: Vec<&MatroskaTrack> = tracks.iter().collect();
+
+        let preferences = [Preference {
+            language: "eng".to_string(),
+            name: Some("full".to_string()),
+            ..Default::default()
+        }];
+        let edits = Selector::Preferences(&preferences).plan(tracks.clone());
+        assert_eq!(
+            edits
+                .iter()
+                .map(|e| (e.track.id, e.default == Some(true)))
+                .collect::<Vec<_>>(),
+            vec![(2, false), (1, true)]
+        );
+
+        let preferences = [Preference {
+            language: "jpn".to_string(),
+            ..Default::default()
+        }];
+        assert!(Selector::Preferences(&preferences).plan(tracks).is_empty());
+        assert!(Selector::from_preferences(&[]).is_none());
+    }
+
+    #[test]
+    fn test_selector_same_fallback() {
+        let mut signs = track(1, false);
+        signs.name = Some("Signs".to_string());
+        let tracks = [track(2, false), signs];
+        let tracks: Vec<&MatroskaTrack> = tracks.iter().collect();
+
+        let selector = Selector::Same(vec![
+            Same::new("eng", "en", Some("Dialogue".to_string())),
+            Same::new("eng", "en", Some("Signs".to_string())),
+        ]);
+        let edits = selector.plan(tracks.clone());
+        assert_eq!(
+            edits
+                .iter()
+                .map(|e| (e.track.id, e.default == Some(true)))
+                .collect::<Vec<_>>(),
+            vec![(2, false), (1, true)]
+        );
+
+        let selector = Selector::Same(vec![Same::new("jpn", "ja", None)]);
+        assert!(selector.plan(tracks).is_empty());
+    }
+
+    #[test]
+    fn test_selector_disabled() {
+        let tracks = [track(1, true), track(2, false)];
+        let tracks: Vec<&MatroskaTrack> = tracks.iter().collect();
+
+        let mut edits = Selector::Disabled.plan(tracks);
+        apply_forced(&mut edits, ForcedMode::Set);
+        assert_eq!(
+            FilePlan::new("a.mkv", edits).args().unwrap(),
+            " --edit track:2 --set flag-default=0 --set flag-forced=0 --edit track:3 --set flag-default=0 --set flag-forced=0"
+        );
+    }
+
+    #[test]
+    fn test_apply_forced() {
+        let tracks = [track(1, true), track(2, false)];
+        let mut edits = vec![
+            TrackEdit::new(&tracks[0], false),
+            TrackEdit::new(&tracks[1], true),
+        ];
+
+        apply_forced(&mut edits, ForcedMode::Set);
+        assert_eq!(
+            FilePlan::new("a.mkv", edits.clone()).args().unwrap(),
+            " --edit track:2 --set flag-default=0 --set flag-forced=0 --edit track:3 --set flag-default=1 --set flag-forced=1"
+        );
+
+        apply_forced(&mut edits, ForcedMode::Clear);
+        assert_eq!(edits[1].forced, Some(false));
+
+        apply_forced(&mut edits, ForcedMode::Keep);
+        assert_eq!(
+            FilePlan::new("a.mkv", edits).args().unwrap(),
+            " --edit track:2 --set flag-default=0 --edit track:3 --set flag-default=1"
+        );
+    }
+
+    #[test]
+    fn test_plan_unforced() {
+        let mut forced = track(2, false);
+        forced.forced = true;
+        let tracks = [track(1, true), forced];
+
+        let edits = plan_unforced(tracks.iter().collect());
+        assert_eq!(
+            FilePlan::new("a.mkv", edits).args().unwrap(),
+            " --edit track:3 --set flag-forced=0"
+        );
+    }
+
+    #[test]
+    fn test_merge_edits() {
+        let tracks = [track(1, true), track(2, false)];
+        let mut edits = vec![TrackEdit::new(&tracks[0], false)];
+        let flag = |track| TrackEdit {
+            flags: vec![(Flag::HearingImpaired, true)],
+            ..TrackEdit::untouched(track)
+        };
+
+        merge_edits(&mut edits, vec![flag(&tracks[0]), flag(&tracks[1])]);
+        assert_eq!(
+            FilePlan::new("a.mkv", edits).args().unwrap(),
+            " --edit track:2 --set flag-default=0 --set flag-hearing-impaired=1 --edit track:3 --set flag-hearing-impaired=1"
+        );
+    }
+
+    #[test]
+    fn test_shell_quote() {
+        assert_eq!(shell_quote("track:2"), "track:2");
+        assert_eq!(shell_quote("./Show 01.mkv"), "'./Show 01.mkv'");
+        assert_eq!(shell_quote("It's.mkv"), r"'It'\''s.mkv'");
+        assert_eq!(shell_quote(""), "''");
+    }
+
+    #[test]
+    fn test_file_plan_to_shell() {
+        let tracks = [track(1, true), track(2, false)];
+        let plan = FilePlan::new(
+            "./Show 01.mkv",
+            vec![
+                TrackEdit::new(&tracks[0], false),
+                TrackEdit::new(&tracks[1], true),
+            ],
+        );
+        assert_eq!(
+            plan.to_shell().unwrap(),
+            "mkvpropedit './Show 01.mkv' --edit track:2 --set flag-default=0 --edit track:3 --set flag-default=1"
+        );
+    }
+
+    #[test]
+    fn test_file_plan_remux_to_shell() {
+        let tracks = [track(1, true), track(2, false)];
+        let plan = FilePlan {
+            backend: Backend::Remux(&tracks),
+            ..FilePlan::new(
+                "./Show 01.mkv",
+                vec![
+                    TrackEdit::new(&tracks[0], false),
+                    TrackEdit::new(&tracks[1], true),
+                ],
+            )
+        };
+        assert_eq!(
+            plan.to_shell().unwrap(),
+            "mkvmerge -o './.Show 01.mkv.remux.tmp' --default-track-flag 1:0 --default-track-flag 2:1 --track-order 0:2,0:1 './Show 01.mkv'"
+        );
+    }
+
+    #[test]
+    fn test_file_plan_unselectable() {
+        let mut tracks = [track(1, true)];
+        tracks[0].number = None;
+        let plan = FilePlan::new("a.mkv", vec![TrackEdit::new(&tracks[0], false)]);
+        assert!(matches!(
+            plan.command(),
+            Err(TempError::UnselectableTrack(1))
+        ));
+
+        let plan = FilePlan {
+            backend: Backend::Remux(&tracks),
+            ..plan
+        };
+        assert!(plan.command().is_ok());
+    }
+
+    #[test]
+    fn test_file_plan_to_json() {
+        let tracks = [track(1, true)];
+        let plan = FilePlan::new("a.mkv", vec![TrackEdit::new(&tracks[0], false)]);
+        let json: serde_json::Value = serde_json::from_str(&plan.to_json().unwrap()).unwrap();
+        assert_eq!(
+            json["argv"],
+            serde_json::json!([
+                "mkvpropedit",
+                "a.mkv",
+                "--edit",
+                "track:2",
+                "--set",
+                "flag-default=0"
+            ])
+        );
+        assert_eq!(json["tracks"][0]["default"], true);
+        assert_eq!(json["tracks"][0]["planned_default"], false);
+    }
+
+    #[test]
+    fn test_file_plan_inventory() {
+        let tracks = [
+            track(1, true),
+            MatroskaTrack {
+                type_: MatroskaTrackType::Other("buttons".to_string()),
+                ..track(2, false)
+            },
+            track(3, false),
+        ];
+        let plan = FilePlan {
+            tracks: &tracks,
+            ..FilePlan::new("a.mkv", vec![TrackEdit::new(&tracks[2], true)])
+        };
+        let json: serde_json::Value = serde_json::from_str(&plan.to_json().unwrap()).unwrap();
+        let tracks: Vec<(u64, &str, bool)> = json["tracks"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|track| {
+                (
+                    track["id"].as_u64().unwrap(),
+                    track["type"].as_str().unwrap(),
+                    track["planned_default"].as_bool().unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            tracks,
+            vec![
+                (1, "subtitle", true),
+                (2, "buttons", false),
+                (3, "subtitle", true)
+            ]
+        );
+        assert_eq!(plan.args().unwrap(), " --edit track:4 --set flag-default=1");
+    }
+}
@@ -0,0 +1,221 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::config::{Config, Profile};
+use crate::error::{TempError, TempResult};
+use crate::plan::ForcedMode;
+use crate::preference::Preference;
+use crate::rule::Rule;
+
+/// Name of the policy files, a policy applies to every file under its directory
+pub const POLICY_FILE_NAME: &str = ".mkvdefault.toml";
+
+/// Content of a policy file, a child policy overrides the fields set by its parents
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Policy {
+    /// Profile of the config file, the inherited preferences are replaced by the profile
+    pub profile: Option<String>,
+    pub audio: Option<Vec<Preference>>,
+    pub subtitle: Option<Vec<Preference>>,
+    pub video: Option<Vec<Preference>>,
+    pub forced: Option<ForcedMode>,
+    pub rules: Option<Vec<Rule>>,
+    /// Original language of the content, set on the tracks with '--tag-original'
+    pub original: Option<String>,
+}
+
+/// Policies of the directories, cached by directory
+#[derive(Debug, Default)]
+pub struct Policies {
+    cache: HashMap<PathBuf, Option<Policy>>,
+}
+
+impl Policy {
+    /// Create a Policy from a string
+    pub fn from_string(path: &Path, input: &str) -> TempResult<Self> {
+        toml::from_str(input).map_err(|err| TempError::Config(path.to_owned(), err))
+    }
+
+    /// Override the fields of the policy with the fields set in the 'child' policy
+    pub fn merge(&self, child: &Policy) -> Policy {
+        if child.profile.is_some() {
+            return child.clone();
+        }
+        Policy {
+            profile: self.profile.clone(),
+            audio: child.audio.clone().or_else(|| self.audio.clone()),
+            subtitle: child.subtitle.clone().or_else(|| self.subtitle.clone()),
+            video: child.video.clone().or_else(|| self.video.clone()),
+            forced: child.forced.or(self.forced),
+            rules: child.rules.clone().or_else(|| self.rules.clone()),
+            original: child.original.clone().or_else(|| self.original.clone()),
+        }
+    }
+
+    /// Get the preferences of the policy, the profile is read from the config
+    pub fn to_profile(&self, config: &Config) -> TempResult<Profile> {
+        let profile = match &self.profile {
+            Some(name) => config.profile(name)?.clone(),
+            None => Profile::default(),
+        };
+        Ok(Profile {
+            audio: self.audio.clone().unwrap_or(profile.audio),
+            subtitle: self.subtitle.clone().unwrap_or(profile.subtitle),
+            video: self.video.clone().unwrap_or(profile.video),
+            forced: self.forced.or(profile.forced),
+            rules: self.rules.clone().unwrap_or(profile.rules),
+        })
+    }
+}
+
+impl Policies {
+    /// Get the policy of the file, inherited from the policies of its parent directories
+    pub fn get(&mut self, file: &Path) -> TempResult<Option<Policy>> {
+        let dir = match file.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        let dir = fs::canonicalize(dir).unwrap_or_else(|_| dir.to_owned());
+        self.get_dir(&dir)
+    }
+
+    fn get_dir(&mut self, dir: &Path) -> TempResult<Option<Policy>> {
+        if let Some(policy) = self.cache.get(dir) {
+            return Ok(policy.clone());
+        }
+
+        let parent = match dir.parent() {
+            Some(parent) => self.get_dir(parent)?,
+            None => None,
+        };
+        let policy = match (parent, read_policy(dir)?) {
+            (Some(parent), Some(policy)) => Some(parent.merge(&policy)),
+            (parent, policy) => policy.or(parent),
+        };
+
+        self.cache.insert(dir.to_owned(), policy.clone());
+        Ok(policy)
+    }
+}
+
+/// Read the policy file of the directory if it exists
+fn read_policy(dir: &Path) -> TempResult<Option<Policy>> {
+    let path = dir.join(POLICY_FILE_NAME);
+    if !path.is_file() {
+        return Ok(None);
+    }
+
+    let input = fs::read_to_string(&path).map_err(|err| TempError::Io(path.to_owned(), err))?;
+    Policy::from_string(&path, &input).map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn preferences(language: &str) -> Option<Vec<Preference>> {
+        Some(vec![Preference {
+            language: language.to_string(),
+            ..Default::default()
+        }])
+    }
+
+    #[test]
+    fn test_policy_merge() {
+        let parent = Policy {
+            audio: preferences("jpn"),
+            original: Some("jpn".to_string()),
+            subtitle: preferences("eng"),
+            ..Default::default()
+        };
+        let child = Policy {
+            subtitle: preferences("fre"),
+            ..Default::default()
+        };
+        let merged = parent.merge(&child);
+        assert_eq!(merged.original.as_deref(), Some("jpn"));
+        assert_eq!(merged.audio, preferences("jpn"));
+        assert_eq!(merged.subtitle, preferences("fre"));
+
+        let child = Policy {
+            profile: Some("anime".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(parent.merge(&child), child);
+    }
+
+    #[test]
+    fn test_policy_to_profile() {
+        let config = Config::from_string(
+            Path::new("config.toml"),
+            r#"
+                [profiles.anime]
+                audio = [{ language = "jpn" }]
+                subtitle = [{ language = "eng" }]
+            "#,
+        )
+        .unwrap();
+        let policy = Policy {
+            profile: Some("anime".to_string()),
+            subtitle: preferences("fre"),
+            ..Default::default()
+        };
+        let profile = policy.to_profile(&config).unwrap();
+        assert_eq!(Some(profile.audio), preferences("jpn"));
+        assert_eq!(Some(profile.subtitle), preferences("fre"));
+    }
+
+    #[test]
+    fn test_policies_inherited() {
+        let dir = tempfile::tempdir().unwrap();
+        let show = dir.path().join("Show");
+        let season = show.join("Season 1");
+        fs::create_dir_all(&season).unwrap();
+        fs::write(
+            show.join(POLICY_FILE_NAME),
+            "audio = [{ language = \"jpn\" }]\nsubtitle = [{ language = \"eng\", name = \"Signs & Songs\" }]",
+        )
+        .unwrap();
+        fs::write(
+            season.join(POLICY_FILE_NAME),
+            "subtitle = [{ language = \"eng\", name = \"Full\" }]",
+        )
+        .unwrap();
+
+        let mut policies = Policies::default();
+        let policy = policies.get(&show.join("a.mkv")).unwrap().unwrap();
+        assert_eq!(policy.audio, preferences("jpn"));
+        assert_eq!(
+            policy.subtitle.unwrap()[0].name.as_deref(),
+            Some("Signs & Songs")
+        );
+
+        let policy = policies.get(&season.join("b.mkv")).unwrap().unwrap();
+        assert_eq!(policy.audio, preferences("jpn"));
+        assert_eq!(policy.subtitle.unwrap()[0].name.as_deref(), Some("Full"));
+
+        assert_eq!(policies.get(&dir.path().join("c.mkv")).unwrap(), None);
+    }
+
+    #[test]
+    fn test_policies_invalid() {
+        let dir = tempfile::tempdir().unwrap();
+        let broken = dir.path().join("Broken");
+        let show = dir.path().join("Show");
+        fs::create_dir_all(&broken).unwrap();
+        fs::create_dir_all(&show).unwrap();
+        fs::write(broken.join(POLICY_FILE_NAME), "audio = [").unwrap();
+        fs::write(show.join(POLICY_FILE_NAME), "original = \"jpn\"").unwrap();
+
+        // Only the files under the invalid policy fail
+        let mut policies = Policies::default();
+        assert!(policies.get(&broken.join("a.mkv")).is_err());
+        let policy = policies.get(&show.join("b.mkv")).unwrap().unwrap();
+        assert_eq!(policy.original.as_deref(), Some("jpn"));
+        assert!(policies.get(&broken.join("c.mkv")).is_err());
+    }
+}
@@ -0,0 +1,356 @@
+use serde::Deserialize;
+
+use crate::accessibility::Accessibility;
+use crate::audio::AudioQuality;
+use crate::codec;
+use crate::language;
+use crate::matroska::MatroskaTrack;
+use crate::video::VideoQuality;
+
+/// Language of the preference that disables every track of the type
+pub const NONE_LANGUAGE: &str = "none";
+
+/// Language of the preference matching the tracks flagged as the original language
+pub const ORIGINAL_LANGUAGE: &str = "original";
+
+/// Track wanted as default, the name is matched case-insensitively as a substring
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Preference {
+    /// Language of the track, any language when empty (e.g. for the videos)
+    #[serde(default)]
+    pub language: String,
+    pub name: Option<String>,
+    #[serde(default)]
+    pub flags: TrackFlags,
+    /// Codecs of the matching tracks from the most to the least wanted (e.g. 'FLAC' or 'text')
+    #[serde(default)]
+    pub codecs: Vec<String>,
+    /// Channels and sampling frequency of the matching audio tracks
+    #[serde(default)]
+    pub audio: AudioQuality,
+    /// Resolution of the matching video tracks
+    #[serde(default)]
+    pub video: VideoQuality,
+    /// Accessibility flags of the matching tracks, set or given by their names (e.g. 'SDH')
+    #[serde(default)]
+    pub accessibility: Accessibility,
+    /// Match the commentaries instead of the other tracks
+    #[serde(default)]
+    pub commentary: bool,
+}
+
+/// Flags the track must have (or not have) to match, unset flags are ignored
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TrackFlags {
+    pub default: Option<bool>,
+    pub forced: Option<bool>,
+}
+
+/// Outcome of the preferences for a file
+#[derive(Debug, Clone, Copy)]
+pub enum Resolution<'a> {
+    /// Track to set as default
+    Track(&'a MatroskaTrack),
+    /// No track should be default
+    Disabled,
+}
+
+impl Preference {
+    /// Does the preference disable every track
+    pub fn is_disabled(&self) -> bool {
+        self.language == NONE_LANGUAGE
+    }
+
+    /// Does the track match the preference
+    pub fn matches(&self, track: &MatroskaTrack) -> bool {
+        let is_language = match self.language.as_str() {
+            "" => true,
+            ORIGINAL_LANGUAGE => track.original,
+            language => language::matches_track(&track.language, &track.language_ietf, language),
+        };
+        is_language
+            && self.matches_name(track.name.as_deref())
+            && self.flags.matches(track)
+            && track.is_commentary() == self.commentary
+    }
+
+    fn matches_name(&self, name: Option<&str>) -> bool {
+        match (&self.name, name) {
+            (None, _) => true,
+            (Some(_), None) => false,
+            (Some(pattern), Some(name)) => name.to_lowercase().contains(&pattern.to_lowercase()),
+        }
+    }
+}
+
+impl TrackFlags {
+    /// Does the track have the wanted flags
+    pub fn matches(&self, track: &MatroskaTrack) -> bool {
+        self.default.is_none_or(|default| track.default == default)
+            && self.forced.is_none_or(|forced| track.forced == forced)
+    }
+}
+
+/// Get the track of the first preference matching one of the tracks,
+/// the best accessibility flags, channels, sampling frequency, resolution then codec win among the matching tracks
+pub fn resolve<'a>(
+    preferences: &[Preference],
+    tracks: &[&'a MatroskaTrack],
+) -> Option<Resolution<'a>> {
+    preferences
+        .iter()
+        .find_map(|preference| match preference.is_disabled() {
+            true => Some(Resolution::Disabled),
+            false => tracks
+                .iter()
+                .filter(|track| preference.matches(track))
+                .min_by_key(|track| {
+                    (
+                        preference.accessibility.rank(track),
+                        preference.audio.rank(track),
+                        preference.video.rank(track),
+                        codec::rank(track, &preference.codecs),
+                    )
+                })
+                .map(|track| Resolution::Track(track)),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matroska::MatroskaTrackType;
+
+    fn track(id: usize, language: &str, language_ietf: &str, name: Option<&str>) -> MatroskaTrack {
+        MatroskaTrack {
+            id,
+            name: name.map(|name| name.to_string()),
+            type_: MatroskaTrackType::Subtitles,
+            language: language.to_string(),
+            language_ietf: language_ietf.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn preference(language: &str, name: Option<&str>) -> Preference {
+        Preference {
+            language: language.to_string(),
+            name: name.map(|name| name.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_matches_name() {
+        let track = track(1, "eng", "en", Some("English Full Subs"));
+        assert!(preference("eng", Some("full")).matches(&track));
+        assert!(preference("en", None).matches(&track));
+        assert!(!preference("eng", Some("Signs")).matches(&track));
+        assert!(!preference("jpn", None).matches(&track));
+    }
+
+    #[test]
+    fn test_matches_flags() {
+        let mut track = track(1, "eng", "en", None);
+        let mut preference = preference("eng", None);
+        preference.flags.default = Some(true);
+        assert!(!preference.matches(&track));
+
+        track.default = true;
+        assert!(preference.matches(&track));
+    }
+
+    #[test]
+    fn test_resolve_fallback() {
+        let tracks = [
+            track(1, "eng", "en", Some("Signs & Songs")),
+            track(2, "eng", "en", Some("Dialogue")),
+            track(3, "fre", "fr", Some("Full")),
+        ];
+        let tracks: Vec<&MatroskaTrack> = tracks.iter().collect();
+
+        let preferences = [preference("eng", Some("Full")), preference("eng", None)];
+        assert!(matches!(resolve(&preferences, &tracks), Some(Resolution::Track(t)) if t.id == 1));
+
+        let preferences = [preference("fre", Some("Full")), preference("eng", None)];
+        assert!(matches!(resolve(&preferences, &tracks), Some(Resolution::Track(t)) if t.id == 3));
+
+        let preferences = [preference("jpn", None)];
+        assert!(resolve(&preferences, &tracks).is_none());
+
+        let preferences = [preference("jpn", None), preference(NONE_LANGUAGE, None)];
+        assert!(matches!(
+            resolve(&preferences, &tracks),
+            Some(Resolution::Disabled)
+        ));
+    }
+
+    #[test]
+    fn test_resolve_codecs() {
+        let with_codec = |id: usize, codec_id: &str| MatroskaTrack {
+            codec_id: Some(codec_id.to_string()),
+            ..track(id, "eng", "en", None)
+        };
+        let tracks = [
+            with_codec(1, "S_HDMV/PGS"),
+            with_codec(2, "S_TEXT/ASS"),
+            with_codec(3, "A_AAC"),
+            with_codec(4, "A_FLAC"),
+        ];
+        let tracks: Vec<&MatroskaTrack> = tracks.iter().collect();
+
+        let mut preference = preference("eng", None);
+        assert!(
+            matches!(resolve(&[preference.clone()], &tracks), Some(Resolution::Track(t)) if t.id == 1)
+        );
+
+        preference.codecs = vec!["text".to_string()];
+        assert!(
+            matches!(resolve(&[preference.clone()], &tracks), Some(Resolution::Track(t)) if t.id == 2)
+        );
+
+        preference.codecs = ["FLAC", "TrueHD", "AC3", "AAC"].map(String::from).to_vec();
+        assert!(matches!(resolve(&[preference], &tracks), Some(Resolution::Track(t)) if t.id == 4));
+    }
+
+    #[test]
+    fn test_resolve_channels() {
+        let with_channels = |id: usize, channels: u64| MatroskaTrack {
+            audio_channels: Some(channels),
+            type_: MatroskaTrackType::Audio,
+            ..track(id, "jpn", "ja", None)
+        };
+        let mut preference = preference("jpn", None);
+        preference.audio.min_channels = Some(6);
+
+        let tracks = [
+            with_channels(1, 2),
+            with_channels(2, 6),
+            with_channels(3, 8),
+        ];
+        let tracks: Vec<&MatroskaTrack> = tracks.iter().collect();
+        assert!(
+            matches!(resolve(&[preference.clone()], &tracks), Some(Resolution::Track(t)) if t.id == 2)
+        );
+
+        let tracks = [
+            with_channels(1, 2),
+            with_channels(2, 4),
+            with_channels(3, 1),
+        ];
+        let tracks: Vec<&MatroskaTrack> = tracks.iter().collect();
+        assert!(matches!(resolve(&[preference], &tracks), Some(Resolution::Track(t)) if t.id == 2));
+    }
+
+    #[test]
+    fn test_resolve_accessibility() {
+        let tracks = [
+            track(1, "eng", "en", Some("English SDH")),
+            track(2, "eng", "en", Some("English")),
+        ];
+        let tracks: Vec<&MatroskaTrack> = tracks.iter().collect();
+
+        let mut preference = preference("eng", None);
+        preference.accessibility.hearing_impaired = Some(false);
+        assert!(
+            matches!(resolve(&[preference.clone()], &tracks), Some(Resolution::Track(t)) if t.id == 2)
+        );
+
+        preference.accessibility.hearing_impaired = Some(true);
+        assert!(matches!(resolve(&[preference], &tracks), Some(Resolution::Track(t)) if t.id == 1));
+    }
+
+    #[test]
+    fn test_resolve_video() {
+        let video = |id: usize, name: &str, codec: &str, dimensions: &str| MatroskaTrack {
+            type_: MatroskaTrackType::Video,
+            codec: Some(codec.to_string()),
+            video_pixel_dimensions: Some(dimensions.to_string()),
+            ..track(id, "und", "und", Some(name))
+        };
+        let tracks = [
+            video(1, "SD", "AVC/H.264/MPEG-4p10", "720x480"),
+            video(2, "HD", "AVC/H.264/MPEG-4p10", "1920x1080"),
+            video(3, "UHD", "HEVC/H.265/MPEG-H", "3840x2160"),
+        ];
+        let tracks: Vec<&MatroskaTrack> = tracks.iter().collect();
+
+        let mut hd = Preference::default();
+        hd.video.height = Some(1080);
+        assert!(matches!(resolve(&[hd], &tracks), Some(Resolution::Track(t)) if t.id == 2));
+
+        let hevc = Preference {
+            codecs: vec!["HEVC".to_string()],
+            ..Default::default()
+        };
+        assert!(matches!(resolve(&[hevc], &tracks), Some(Resolution::Track(t)) if t.id == 3));
+
+        let preferences = [preference("", Some("sd"))];
+        assert!(matches!(resolve(&preferences, &tracks), Some(Resolution::Track(t)) if t.id == 1));
+    }
+
+    #[test]
+    fn test_resolve_original() {
+        let tracks = [
+            track(1, "eng", "en", None),
+            MatroskaTrack {
+                original: true,
+                ..track(2, "jpn", "ja", None)
+            },
+        ];
+        let tracks: Vec<&MatroskaTrack> = tracks.iter().collect();
+
+        let preferences = [preference(ORIGINAL_LANGUAGE, None), preference("eng", None)];
+        assert!(matches!(resolve(&preferences, &tracks), Some(Resolution::Track(t)) if t.id == 2));
+
+        let tracks = &tracks[..1];
+        assert!(matches!(resolve(&preferences, tracks), Some(Resolution::Track(t)) if t.id == 1));
+    }
+
+    #[test]
+    fn test_resolve_commentary() {
+        let tracks = [
+            track(1, "eng", "en", Some("Director's Commentary")),
+            MatroskaTrack {
+                commentary: true,
+                ..track(2, "eng", "en", None)
+            },
+            track(3, "eng", "en", None),
+        ];
+        let tracks: Vec<&MatroskaTrack> = tracks.iter().collect();
+
+        let mut preference = preference("eng", None);
+        assert!(
+            matches!(resolve(&[preference.clone()], &tracks), Some(Resolution::Track(t)) if t.id == 3)
+        );
+
+        preference.commentary = true;
+        assert!(matches!(resolve(&[preference], &tracks), Some(Resolution::Track(t)) if t.id == 1));
+    }
+
+    #[test]
+    fn test_resolve_region_and_script() {
+        let tracks = [
+            track(1, "chi", "zh-Hans", None),
+            track(2, "chi", "zh-Hant-TW", None),
+            track(3, "por", "pt-PT", None),
+            track(4, "por", "pt-BR", None),
+            track(5, "mul", "und", None),
+        ];
+        let tracks: Vec<&MatroskaTrack> = tracks.iter().collect();
+
+        let preferences = [preference("zh-Hant", None), preference("zh-Hans", None)];
+        assert!(matches!(resolve(&preferences, &tracks), Some(Resolution::Track(t)) if t.id == 2));
+
+        let preferences = [preference("pt-BR", None), preference("pt", None)];
+        assert!(matches!(resolve(&preferences, &tracks), Some(Resolution::Track(t)) if t.id == 4));
+
+        let preferences = [preference("por", None)];
+        assert!(matches!(resolve(&preferences, &tracks), Some(Resolution::Track(t)) if t.id == 3));
+
+        let preferences = [preference("mul", None)];
+        assert!(matches!(resolve(&preferences, &tracks), Some(Resolution::Track(t)) if t.id == 5));
+    }
+}
@@ -0,0 +1,363 @@
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+use crate::error::{TempError, TempResult};
+use crate::language;
+use crate::matroska::{MatroskaTrack, MatroskaTrackType};
+use crate::plan::{FilePlan, Flag, TrackEdit};
+use crate::{identify_file, run_tool};
+
+/// Get the path of the remuxed file, next to the original so the rename is atomic
+pub fn temp_path(path: &Path) -> PathBuf {
+    let mut name = OsString::from(".");
+    name.push(path.file_name().unwrap_or_default());
+    name.push(".remux.tmp");
+    path.with_file_name(name)
+}
+
+/// Get the edit of the track, if it has one
+fn find_edit<'e, 'a>(
+    track: &MatroskaTrack,
+    edits: &'e [TrackEdit<'a>],
+) -> Option<&'e TrackEdit<'a>> {
+    edits.iter().find(|edit| std::ptr::eq(edit.track, track))
+}
+
+/// Get the flag the track has once the edit is applied
+fn planned_default(track: &MatroskaTrack, edits: &[TrackEdit]) -> bool {
+    find_edit(track, edits)
+        .and_then(|edit| edit.default)
+        .unwrap_or(track.default)
+}
+
+/// Get the forced flag the track has once the edit is applied
+fn planned_forced(track: &MatroskaTrack, edits: &[TrackEdit]) -> bool {
+    find_edit(track, edits)
+        .and_then(|edit| edit.forced)
+        .unwrap_or(track.forced)
+}
+
+/// Get the value of the other flag once the edit is applied
+fn planned_flag(flag: Flag, track: &MatroskaTrack, edits: &[TrackEdit]) -> bool {
+    find_edit(track, edits)
+        .and_then(|edit| {
+            edit.flags
+                .iter()
+                .find(|(edited, _)| *edited == flag)
+                .map(|(_, value)| *value)
+        })
+        .unwrap_or(flag.get(track))
+}
+
+/// Get the tracks in their remuxed order: videos, audios then subtitles, the default track first of its type
+pub fn track_order<'a>(tracks: &'a [MatroskaTrack], edits: &[TrackEdit]) -> Vec<&'a MatroskaTrack> {
+    let type_rank = |track: &MatroskaTrack| match track.type_ {
+        MatroskaTrackType::Video => 0,
+        MatroskaTrackType::Audio => 1,
+        MatroskaTrackType::Subtitles => 2,
+        MatroskaTrackType::Other(_) => 3,
+    };
+    let mut order: Vec<&MatroskaTrack> = tracks.iter().collect();
+    order.sort_by_key(|track| (type_rank(track), !planned_default(track, edits)));
+    order
+}
+
+/// Get the mkvmerge args of the edit, the flags are set on the track id of the source file
+fn edit_args(edit: &TrackEdit) -> Vec<String> {
+    let id = edit.track.id;
+    let mut args = vec![];
+    if let Some(default) = edit.default {
+        args.extend([
+            "--default-track-flag".to_owned(),
+            format!("{id}:{}", default as u8),
+        ]);
+    }
+    if let Some(forced) = edit.forced {
+        args.extend([
+            "--forced-display-flag".to_owned(),
+            format!("{id}:{}", forced as u8),
+        ]);
+    }
+    for (flag, value) in &edit.flags {
+        args.extend([flag.option().to_owned(), format!("{id}:{}", *value as u8)]);
+    }
+    if let Some((_, language_ietf)) = &edit.language {
+        args.extend(["--language".to_owned(), format!("{id}:{language_ietf}")]);
+    }
+    args
+}
+
+/// Generate the mkvmerge command writing the file to its temporary path with the tracks reordered
+pub fn generate_remux_command(
+    path: &str,
+    tracks: &[MatroskaTrack],
+    edits: &[TrackEdit],
+) -> Command {
+    let order = track_order(tracks, edits)
+        .iter()
+        .map(|track| format!("0:{}", track.id))
+        .collect::<Vec<String>>()
+        .join(",");
+
+    let mut command = Command::new("mkvmerge");
+    command
+        .arg("-o")
+        .arg(temp_path(Path::new(path)))
+        .args(edits.iter().flat_map(edit_args))
+        .arg("--track-order")
+        .arg(order)
+        .arg(path);
+    command
+}
+
+/// Refuse to remux the file if its folder has not enough free space for a copy of it
+pub fn check_space(path: &Path, size: u64) -> TempResult {
+    let folder = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let available =
+        fs4::available_space(folder).map_err(|err| TempError::Io(folder.to_owned(), err))?;
+
+    // A margin for the headers and the cues rewritten by mkvmerge
+    let needed = size.saturating_add(size / 100);
+    match available < needed {
+        true => Err(TempError::NotEnoughSpace(
+            path.to_owned(),
+            needed,
+            available,
+        )),
+        false => Ok(()),
+    }
+}
+
+/// Refuse to remux the file if its folder has not enough free space for a copy of it, from the size of the file
+pub fn check_file_space(path: &Path) -> TempResult {
+    let size = fs::metadata(path)
+        .map_err(|err| TempError::Io(path.to_owned(), err))?
+        .len();
+    check_space(path, size)
+}
+
+/// Does the remuxed file have the tracks in the planned order with the planned flags and languages,
+/// the tagged tracks already have their planned language
+fn check_remuxed(remuxed: &[MatroskaTrack], order: &[&MatroskaTrack], edits: &[TrackEdit]) -> bool {
+    remuxed.len() == order.len()
+        && remuxed.iter().zip(order).all(|(remuxed, track)| {
+            remuxed.type_ == track.type_
+                && remuxed.codec_id == track.codec_id
+                && remuxed.default == planned_default(track, edits)
+                && remuxed.forced == planned_forced(track, edits)
+                && Flag::ALL
+                    .iter()
+                    .all(|flag| flag.get(remuxed) == planned_flag(*flag, track, edits))
+                && language::canonical(&remuxed.language, &remuxed.language_ietf)
+                    == language::canonical(&track.language, &track.language_ietf)
+        })
+}
+
+/// Remux the file to its temporary path, check it and replace the original with it,
+/// the original is left untouched if mkvmerge fails or if the remuxed file is not the planned one,
+/// the output is the one of a success, with its warnings if any
+pub fn remux(plan: &FilePlan, tracks: &[MatroskaTrack]) -> TempResult<Output> {
+    let path = Path::new(plan.path);
+    let temp = temp_path(path);
+    // The space is checked again, the previous remuxes of the batch may have used it
+    check_file_space(path)?;
+
+    let mut output = run_tool(&mut plan.command()?)?;
+    // mkvmerge prints its warnings and errors on stdout
+    if !output.status.success() && output.stderr.is_empty() {
+        output.stderr = output.stdout.clone();
+    }
+    // mkvmerge exits with '1' on warnings, the file is written and checked like on a success
+    if !matches!(output.status.code(), Some(0) | Some(1)) {
+        let _ = fs::remove_file(&temp);
+        return Err(TempError::ToolFailed(
+            "mkvmerge".to_owned(),
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim().to_owned(),
+        ));
+    }
+
+    let order = track_order(tracks, &plan.edits);
+    let remuxed = identify_file(&temp);
+    if !remuxed.is_ok_and(|remuxed| check_remuxed(&remuxed.tracks, &order, &plan.edits)) {
+        let _ = fs::remove_file(&temp);
+        return Err(TempError::RemuxMismatch(path.to_owned()));
+    }
+
+    fs::rename(&temp, path).map_err(|err| TempError::Io(path.to_owned(), err))?;
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track(id: usize, type_: MatroskaTrackType, default: bool) -> MatroskaTrack {
+        MatroskaTrack {
+            id,
+            type_,
+            default,
+            language: "eng".to_string(),
+            language_ietf: "en".to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn tracks() -> Vec<MatroskaTrack> {
+        vec![
+            track(0, MatroskaTrackType::Video, true),
+            track(1, MatroskaTrackType::Audio, true),
+            track(2, MatroskaTrackType::Subtitles, false),
+            track(3, MatroskaTrackType::Audio, false),
+            track(4, MatroskaTrackType::Subtitles, false),
+        ]
+    }
+
+    #[test]
+    fn test_temp_path() {
+        assert_eq!(
+            temp_path(Path::new("Show/Episode 01.mkv")),
+            PathBuf::from("Show/.Episode 01.mkv.remux.tmp")
+        );
+        assert_eq!(
+            temp_path(Path::new("a.mkv")),
+            PathBuf::from(".a.mkv.remux.tmp")
+        );
+    }
+
+    #[test]
+    fn test_track_order() {
+        let tracks = tracks();
+        let edits = [
+            TrackEdit::new(&tracks[1], false),
+            TrackEdit::new(&tracks[3], true),
+            TrackEdit::new(&tracks[2], false),
+            TrackEdit::new(&tracks[4], true),
+        ];
+        let order: Vec<usize> = track_order(&tracks, &edits)
+            .iter()
+            .map(|track| track.id)
+            .collect();
+        assert_eq!(order, vec![0, 3, 1, 4, 2]);
+
+        let order: Vec<usize> = track_order(&tracks, &[])
+            .iter()
+            .map(|track| track.id)
+            .collect();
+        assert_eq!(order, vec![0, 1, 3, 2, 4]);
+    }
+
+    #[test]
+    fn test_generate_remux_command() {
+        let tracks = tracks();
+        let edits = [
+            TrackEdit::new(&tracks[1], false),
+            TrackEdit {
+                forced: Some(true),
+                flags: vec![(Flag::Original, true)],
+                ..TrackEdit::new(&tracks[3], true)
+            },
+        ];
+        let command = generate_remux_command("Show/a b.mkv", &tracks, &edits);
+        let args: Vec<String> = command
+            .get_args()
+            .map(|arg| arg.to_string_lossy().to_string())
+            .collect();
+        assert_eq!(command.get_program(), "mkvmerge");
+        assert_eq!(
+            args,
+            vec![
+                "-o",
+                "Show/.a b.mkv.remux.tmp",
+                "--default-track-flag",
+                "1:0",
+                "--default-track-flag",
+                "3:1",
+                "--forced-display-flag",
+                "3:1",
+                "--original-flag",
+                "3:1",
+                "--track-order",
+                "0:0,0:3,0:1,0:2,0:4",
+                "Show/a b.mkv",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_check_remuxed() {
+        let tracks = tracks();
+        let edits = [
+            TrackEdit::new(&tracks[1], false),
+            TrackEdit::new(&tracks[3], true),
+        ];
+        let order = track_order(&tracks, &edits);
+        let remuxed = vec![
+            track(0, MatroskaTrackType::Video, true),
+            track(1, MatroskaTrackType::Audio, true),
+            track(2, MatroskaTrackType::Audio, false),
+            track(3, MatroskaTrackType::Subtitles, false),
+            track(4, MatroskaTrackType::Subtitles, false),
+        ];
+        assert!(check_remuxed(&remuxed, &order, &edits));
+        assert!(!check_remuxed(&remuxed, &order, &[]));
+        assert!(!check_remuxed(&remuxed[..4], &order, &edits));
+    }
+
+    #[test]
+    fn test_check_remuxed_edits() {
+        let mut tracks = tracks();
+        // The tagged track already has its planned language
+        tracks[3].language = "fre".to_string();
+        tracks[3].language_ietf = "fr".to_string();
+        let edits = [
+            TrackEdit::new(&tracks[1], false),
+            TrackEdit {
+                forced: Some(true),
+                flags: vec![(Flag::Original, true)],
+                language: Some(("fre".to_string(), "fr".to_string())),
+                ..TrackEdit::new(&tracks[3], true)
+            },
+        ];
+        let order = track_order(&tracks, &edits);
+        let mut remuxed = vec![
+            track(0, MatroskaTrackType::Video, true),
+            track(1, MatroskaTrackType::Audio, true),
+            track(2, MatroskaTrackType::Audio, false),
+            track(3, MatroskaTrackType::Subtitles, false),
+            track(4, MatroskaTrackType::Subtitles, false),
+        ];
+        assert!(!check_remuxed(&remuxed, &order, &edits));
+
+        remuxed[1].forced = true;
+        remuxed[1].original = true;
+        assert!(!check_remuxed(&remuxed, &order, &edits));
+
+        remuxed[1].language = "fre".to_string();
+        remuxed[1].language_ietf = "fr".to_string();
+        assert!(check_remuxed(&remuxed, &order, &edits));
+    }
+
+    #[test]
+    fn test_check_space() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.mkv");
+        assert!(check_space(&path, 0).is_ok());
+        assert!(matches!(
+            check_space(&path, u64::MAX / 2),
+            Err(TempError::NotEnoughSpace(_, _, _))
+        ));
+
+        fs::write(&path, b"matroska").unwrap();
+        assert!(check_file_space(&path).is_ok());
+        assert!(matches!(
+            check_file_space(&dir.path().join("missing.mkv")),
+            Err(TempError::Io(_, _))
+        ));
+    }
+}
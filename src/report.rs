@@ -0,0 +1,173 @@
+use std::fs;
+use std::path::Path;
+use std::process::Output;
+use std::time::Duration;
+
+use serde::Serialize;
+
+use crate::error::{TempError, TempResult};
+use crate::plan::{FilePlan, SerializeTrackEdit};
+use crate::ResultCommand;
+
+/// Structured results of a run
+#[derive(Debug, Default, Serialize)]
+pub struct Report<'a> {
+    pub files: Vec<FileReport<'a>>,
+    pub identify_errors: Vec<ErrorReport>,
+    /// Files that failed before being planned, e.g. under a policy that can't be read
+    pub errors: Vec<ErrorReport>,
+    pub skipped: Vec<String>,
+}
+
+/// File that failed without a plan, with its error
+#[derive(Debug, Serialize)]
+pub struct ErrorReport {
+    pub path: String,
+    pub error: String,
+}
+
+/// Result of the mkvpropedit command of a file
+#[derive(Debug, Serialize)]
+pub struct FileReport<'a> {
+    pub path: &'a str,
+    pub tracks: Vec<SerializeTrackEdit<'a>>,
+    pub success: bool,
+    pub exit_status: Option<i32>,
+    pub stderr: String,
+    pub duration_ms: u128,
+}
+
+impl<'a> FileReport<'a> {
+    pub fn new(plan: &FilePlan<'a>, output: &Output, success: bool, duration: Duration) -> Self {
+        Self {
+            path: plan.path,
+            tracks: plan.serialize_tracks(),
+            success,
+            exit_status: output.status.code(),
+            stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+            duration_ms: duration.as_millis(),
+        }
+    }
+
+    /// Get the result of a file that failed before or without an exit status, the error is reported as its stderr
+    pub fn failed(plan: &FilePlan<'a>, error: &TempError, duration: Duration) -> Self {
+        Self {
+            path: plan.path,
+            tracks: plan.serialize_tracks(),
+            success: false,
+            exit_status: None,
+            stderr: error.to_string(),
+            duration_ms: duration.as_millis(),
+        }
+    }
+}
+
+impl Report<'_> {
+    /// Add the files that were not edited
+    pub fn add_results(&mut self, results: &[ResultCommand]) {
+        for result in results {
+            match result {
+                ResultCommand::IdentifyError(path, error) => {
+                    self.identify_errors.push(ErrorReport {
+                        path: path.to_owned(),
+                        error: error.to_owned(),
+                    })
+                }
+                // The files that failed once planned are already reported with their tracks
+                ResultCommand::Error(path, error)
+                    if !self.files.iter().any(|file| file.path == path) =>
+                {
+                    self.errors.push(ErrorReport {
+                        path: path.to_owned(),
+                        error: error.to_owned(),
+                    })
+                }
+                ResultCommand::Skipped(path) => self.skipped.push(path.to_owned()),
+                ResultCommand::Success(_) | ResultCommand::Error(_, _) => {}
+            }
+        }
+    }
+
+    /// Get the report as a json document
+    pub fn to_json(&self) -> TempResult<String> {
+        serde_json::to_string_pretty(self).map_err(TempError::Serialize)
+    }
+
+    /// Write the report as a json document to 'path'
+    pub fn write(&self, path: &Path) -> TempResult {
+        fs::write(path, self.to_json()?).map_err(|err| TempError::Io(path.to_owned(), err))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matroska::{MatroskaTrack, MatroskaTrackType};
+    use crate::plan::TrackEdit;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_report_to_json() {
+        use std::os::unix::process::ExitStatusExt;
+
+        let track = MatroskaTrack {
+            id: 1,
+            type_: MatroskaTrackType::Audio,
+            language: "jpn".to_string(),
+            language_ietf: "ja".to_string(),
+            ..Default::default()
+        };
+        let plan = FilePlan::new("a.mkv", vec![TrackEdit::new(&track, true)]);
+        let output = Output {
+            status: ExitStatusExt::from_raw(2 << 8),
+            stdout: vec![],
+            stderr: b"Error: no write access".to_vec(),
+        };
+        let mut report = Report {
+            files: vec![FileReport::new(
+                &plan,
+                &output,
+                false,
+                Duration::from_millis(42),
+            )],
+            ..Default::default()
+        };
+        report.add_results(&[
+            ResultCommand::Skipped("b.mkv".to_string()),
+            ResultCommand::IdentifyError("c.mkv".to_string(), "Unrecognized".to_string()),
+            ResultCommand::Error("a.mkv".to_string(), "Error: no write access".to_string()),
+            ResultCommand::Error("d.mkv".to_string(), "Invalid policy".to_string()),
+        ]);
+
+        let json: serde_json::Value = serde_json::from_str(&report.to_json().unwrap()).unwrap();
+        let file = &json["files"][0];
+        assert_eq!(file["path"], "a.mkv");
+        assert_eq!(file["success"], false);
+        assert_eq!(file["exit_status"], 2);
+        assert_eq!(file["stderr"], "Error: no write access");
+        assert_eq!(file["duration_ms"], 42);
+        assert_eq!(file["tracks"][0]["default"], false);
+        assert_eq!(file["tracks"][0]["planned_default"], true);
+        assert_eq!(json["skipped"][0], "b.mkv");
+        assert_eq!(json["identify_errors"][0]["path"], "c.mkv");
+        assert_eq!(json["errors"].as_array().unwrap().len(), 1);
+        assert_eq!(json["errors"][0]["path"], "d.mkv");
+    }
+
+    #[test]
+    fn test_file_report_failed() {
+        let track = MatroskaTrack {
+            id: 1,
+            type_: MatroskaTrackType::Audio,
+            ..Default::default()
+        };
+        let plan = FilePlan::new("a.mkv", vec![TrackEdit::new(&track, true)]);
+        let report = FileReport::failed(&plan, &TempError::UnselectableTrack(1), Duration::ZERO);
+        assert!(!report.success);
+        assert_eq!(report.exit_status, None);
+        assert_eq!(
+            report.stderr,
+            "Unable to select the track 1 with mkvpropedit, it has neither a uid nor a number"
+        );
+    }
+}
@@ -0,0 +1,178 @@
+use std::str::FromStr;
+
+use serde::Deserialize;
+
+use crate::language;
+use crate::matroska::MatroskaTrack;
+use crate::plan::{ForcedMode, TrackEdit};
+use crate::preference::{Preference, ORIGINAL_LANGUAGE};
+
+/// Subtitle preferences used when the default audio of a file is in the 'audio' language
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Rule {
+    /// Language of the default audio, a range ('en' matches 'en-US') or 'original'
+    pub audio: String,
+    #[serde(default)]
+    pub subtitle: Vec<Preference>,
+    /// What to do with the forced flag of the subtitles
+    pub forced: Option<ForcedMode>,
+}
+
+impl Rule {
+    /// Is the audio track in the language of the rule
+    pub fn matches(&self, audio: &MatroskaTrack) -> bool {
+        match self.audio.as_str() {
+            ORIGINAL_LANGUAGE => audio.original,
+            language => language::matches_track(&audio.language, &audio.language_ietf, language),
+        }
+    }
+}
+
+/// Parse a rule of the command line: 'AUDIO=SUBTITLE[:NAME][!FORCED]', the forced mode is 'keep', 'set' or 'clear'
+/// (e.g. 'jpn=eng:Full', 'eng=eng:Signs!set' or 'eng=none!clear')
+impl FromStr for Rule {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let expected = || format!("expected 'AUDIO=SUBTITLE[:NAME][!FORCED]', got '{input}'");
+        let (audio, subtitle) = input.split_once('=').ok_or_else(expected)?;
+        // A '!' not followed by a forced mode is part of the name
+        let (subtitle, forced) = match subtitle.rsplit_once('!') {
+            Some((subtitle, "keep")) => (subtitle, Some(ForcedMode::Keep)),
+            Some((subtitle, "set")) => (subtitle, Some(ForcedMode::Set)),
+            Some((subtitle, "clear")) => (subtitle, Some(ForcedMode::Clear)),
+            _ => (subtitle, None),
+        };
+        let (language, name) = match subtitle.split_once(':') {
+            Some((language, name)) => (language, Some(name.to_owned())),
+            None => (subtitle, None),
+        };
+        if audio.is_empty() || language.is_empty() {
+            return Err(expected());
+        }
+
+        Ok(Self {
+            audio: audio.to_owned(),
+            subtitle: vec![Preference {
+                language: language.to_owned(),
+                name,
+                ..Default::default()
+            }],
+            forced,
+        })
+    }
+}
+
+/// Get the audio track that is default once the edits are applied: the planned one,
+/// else the one already default, else the first one (played by default)
+pub fn resolved_audio<'a>(
+    edits: &[TrackEdit<'a>],
+    audios: &[&'a MatroskaTrack],
+) -> Option<&'a MatroskaTrack> {
+    match edits.iter().find(|edit| edit.default.is_some()) {
+        Some(_) => edits
+            .iter()
+            .find(|edit| edit.default == Some(true))
+            .map(|edit| edit.track),
+        None => audios
+            .iter()
+            .find(|track| track.default)
+            .or(audios.first())
+            .copied(),
+    }
+}
+
+/// Get the first rule matching the audio
+pub fn find_rule<'a>(
+    rules: impl IntoIterator<Item = &'a Rule>,
+    audio: Option<&MatroskaTrack>,
+) -> Option<&'a Rule> {
+    let audio = audio?;
+    rules.into_iter().find(|rule| rule.matches(audio))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matroska::MatroskaTrackType;
+
+    fn audio(id: usize, language: &str, default: bool) -> MatroskaTrack {
+        MatroskaTrack {
+            id,
+            type_: MatroskaTrackType::Audio,
+            default,
+            language: language.to_string(),
+            language_ietf: "und".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_from_str() {
+        let rule: Rule = "jpn=eng:Full".parse().unwrap();
+        assert_eq!(rule.audio, "jpn");
+        assert_eq!(rule.subtitle[0].language, "eng");
+        assert_eq!(rule.subtitle[0].name.as_deref(), Some("Full"));
+
+        assert_eq!(rule.forced, None);
+
+        let rule: Rule = "eng=none".parse().unwrap();
+        assert!(rule.subtitle[0].is_disabled());
+
+        let rule: Rule = "eng=eng:Signs & Songs!set".parse().unwrap();
+        assert_eq!(rule.subtitle[0].name.as_deref(), Some("Signs & Songs"));
+        assert_eq!(rule.forced, Some(ForcedMode::Set));
+
+        let rule: Rule = "eng=none!clear".parse().unwrap();
+        assert!(rule.subtitle[0].is_disabled());
+        assert_eq!(rule.forced, Some(ForcedMode::Clear));
+
+        let rule: Rule = "jpn=eng:Wow!".parse().unwrap();
+        assert_eq!(rule.subtitle[0].name.as_deref(), Some("Wow!"));
+        assert_eq!(rule.forced, None);
+
+        assert!("jpn".parse::<Rule>().is_err());
+        assert!("=eng".parse::<Rule>().is_err());
+    }
+
+    #[test]
+    fn test_resolved_audio() {
+        let tracks = [audio(1, "eng", true), audio(2, "jpn", false)];
+        let audios: Vec<&MatroskaTrack> = tracks.iter().collect();
+
+        let edits = [
+            TrackEdit::new(&tracks[0], false),
+            TrackEdit::new(&tracks[1], true),
+        ];
+        assert_eq!(resolved_audio(&edits, &audios).unwrap().id, 2);
+        assert_eq!(resolved_audio(&[], &audios).unwrap().id, 1);
+
+        let tracks = [audio(1, "eng", false), audio(2, "jpn", false)];
+        let audios: Vec<&MatroskaTrack> = tracks.iter().collect();
+        assert_eq!(resolved_audio(&[], &audios).unwrap().id, 1);
+        assert!(resolved_audio(&[], &[]).is_none());
+    }
+
+    #[test]
+    fn test_find_rule() {
+        let rules: Vec<Rule> = ["jpn=eng:Full", "en=none"]
+            .iter()
+            .map(|rule| rule.parse().unwrap())
+            .collect();
+
+        let track = audio(1, "jpn", true);
+        assert_eq!(find_rule(&rules, Some(&track)), Some(&rules[0]));
+
+        let track = audio(1, "eng", true);
+        assert_eq!(find_rule(&rules, Some(&track)), Some(&rules[1]));
+
+        let mut track = audio(1, "fre", true);
+        assert_eq!(find_rule(&rules, Some(&track)), None);
+
+        let rules: Vec<Rule> = vec!["original=none".parse().unwrap()];
+        track.original = true;
+        assert_eq!(find_rule(&rules, Some(&track)), Some(&rules[0]));
+        assert_eq!(find_rule(&rules, None), None);
+    }
+}
@@ -1,10 +1,26 @@
 use std::fmt;
 
+use crate::audio::channel_layout;
+use crate::language::{self, LanguageTag};
+use crate::matroska::MatroskaTrack;
+
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct Same {
     pub language: String,
     pub language_ietf: String,
     pub name: Option<String>,
+    pub codec: Option<String>,
+    pub channels: Option<u64>,
+    pub dimensions: Option<String>,
+    pub commentary: bool,
+}
+
+/// Candidate track with the number of files having it
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Coverage {
+    pub same: Same,
+    pub count: usize,
+    pub total: usize,
 }
 
 impl Same {
@@ -13,26 +29,100 @@ impl Same {
             language: language.to_owned(),
             language_ietf: language_ietf.to_owned(),
             name,
+            codec: None,
+            channels: None,
+            dimensions: None,
+            commentary: false,
         }
     }
+
+    /// Get the candidate of a track
+    pub fn from_track(track: &MatroskaTrack) -> Self {
+        Self {
+            codec: track.codec.to_owned(),
+            channels: track.audio_channels,
+            dimensions: track.video_pixel_dimensions.to_owned(),
+            commentary: track.is_commentary(),
+            ..Self::new(&track.language, &track.language_ietf, track.name.to_owned())
+        }
+    }
+
+    /// Rank a similar track by how close it is to the candidate, the lowest first
+    pub fn rank(&self, track: &MatroskaTrack) -> (bool, bool, bool) {
+        (
+            track.codec != self.codec,
+            track.audio_channels != self.channels,
+            track.video_pixel_dimensions != self.dimensions,
+        )
+    }
+
+    /// Get the canonical language of the candidate
+    pub fn canonical(&self) -> String {
+        language::canonical(&self.language, &self.language_ietf)
+    }
+
+    /// Get the parsed language tag of the candidate
+    pub fn tag(&self) -> LanguageTag {
+        LanguageTag::from_track(&self.language, &self.language_ietf)
+    }
+
+    /// Is the other candidate the same track once the language is normalized
+    pub fn is_equivalent(&self, other: &Same) -> bool {
+        self.canonical() == other.canonical()
+            && self.name == other.name
+            && self.codec == other.codec
+            && self.channels == other.channels
+            && self.dimensions == other.dimensions
+            && self.commentary == other.commentary
+    }
+
+    /// Is the track this candidate, by canonical language, name, codec, channels and dimensions
+    pub fn matches_track(&self, track: &MatroskaTrack) -> bool {
+        self.is_equivalent(&Same::from_track(track))
+    }
+
+    /// Is the track a fallback for this candidate, by canonical language and name only
+    pub fn is_similar_track(&self, track: &MatroskaTrack) -> bool {
+        self.canonical() == language::canonical(&track.language, &track.language_ietf)
+            && track.name == self.name
+            && track.is_commentary() == self.commentary
+    }
 }
 
 impl fmt::Display for Same {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let language = if &self.language_ietf == "und" {
-            &self.language
-        } else {
-            &self.language_ietf
+        let tag = self.tag();
+        let language = match tag.special() {
+            Some(description) => format!("{tag} [{description}]"),
+            None => tag.to_string(),
         };
 
         match &self.name {
-            Some(name) => write!(f, "{language} ({name})"),
-            None => write!(f, "{language}"),
+            Some(name) => write!(f, "{language} ({name})")?,
+            None => write!(f, "{language}")?,
+        }
+        let details: Vec<String> = self
+            .codec
+            .iter()
+            .cloned()
+            .chain(self.channels.map(channel_layout))
+            .chain(self.dimensions.iter().cloned())
+            .chain(self.commentary.then(|| "COMMENTARY".to_owned()))
+            .collect();
+        match details.is_empty() {
+            true => Ok(()),
+            false => write!(f, " [{}]", details.join(", ")),
         }
         // write!(f, "{} <{}>", self.name, self.email)
     }
 }
 
+impl fmt::Display for Coverage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} — {}/{} files", self.same, self.count, self.total)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -62,7 +152,7 @@ mod tests {
         let s = Same::new("es", "es-ES", Some("Spanish".to_owned()));
         assert_eq!(
             format!("{:?}", s),
-            "Same { language: \"es\", language_ietf: \"es-ES\", name: Some(\"Spanish\") }"
+            "Same { language: \"es\", language_ietf: \"es-ES\", name: Some(\"Spanish\"), codec: None, channels: None, dimensions: None, commentary: false }"
         );
     }
 
@@ -73,6 +163,84 @@ mod tests {
         assert_eq!(s1, s2);
     }
 
+    #[test]
+    fn test_matches_track() {
+        let track = MatroskaTrack {
+            id: 1,
+            name: Some("Main".to_string()),
+            type_: crate::matroska::MatroskaTrackType::Audio,
+            language: "jpn".to_string(),
+            language_ietf: "ja".to_string(),
+            ..Default::default()
+        };
+        assert!(Same::new("jpn", "ja", Some("Main".to_owned())).matches_track(&track));
+        assert!(Same::new("jpn", "und", Some("Main".to_owned())).matches_track(&track));
+        assert!(!Same::new("jpn", "ja", None).matches_track(&track));
+        assert!(!Same::new("jpn", "ja-JP", Some("Main".to_owned())).matches_track(&track));
+    }
+
+    #[test]
+    fn test_equivalent_codes() {
+        let s1 = Same::new("fre", "und", None);
+        let s2 = Same::new("fra", "fr", None);
+        assert!(s1.is_equivalent(&s2));
+        assert_eq!(s1.to_string(), "fr");
+        assert_eq!(
+            Same::new("chi", "und", Some("Full".to_owned())).to_string(),
+            "zh (Full)"
+        );
+    }
+
+    #[test]
+    fn test_from_track_codec() {
+        let track = MatroskaTrack {
+            id: 1,
+            codec: Some("FLAC".to_string()),
+            codec_id: Some("A_FLAC".to_string()),
+            audio_channels: Some(6),
+            name: Some("Main".to_string()),
+            type_: crate::matroska::MatroskaTrackType::Audio,
+            language: "jpn".to_string(),
+            language_ietf: "ja".to_string(),
+            ..Default::default()
+        };
+        let s = Same::from_track(&track);
+        assert_eq!(s.to_string(), "ja (Main) [FLAC, 5.1]");
+        assert!(s.matches_track(&track));
+        assert!(!Same::new("jpn", "ja", Some("Main".to_owned())).matches_track(&track));
+        assert!(Same::new("jpn", "ja", Some("Main".to_owned())).is_similar_track(&track));
+        assert!(!s.is_equivalent(&Same::new("jpn", "ja", Some("Main".to_owned()))));
+        assert_eq!(s.rank(&track), (false, false, false));
+        assert_eq!(
+            Same::new("jpn", "ja", Some("Main".to_owned())).rank(&track),
+            (true, true, false)
+        );
+    }
+
+    #[test]
+    fn test_commentary() {
+        let s = Same {
+            commentary: true,
+            ..Same::new("eng", "en", None)
+        };
+        assert_eq!(s.to_string(), "en [COMMENTARY]");
+        assert!(!s.is_equivalent(&Same::new("eng", "en", None)));
+        assert_eq!(
+            Same::new("zxx", "und", None).to_string(),
+            "zxx [no linguistic content]"
+        );
+    }
+
+    #[test]
+    fn test_coverage_display() {
+        let coverage = Coverage {
+            same: Same::new("jpn", "ja", Some("Main".to_owned())),
+            count: 23,
+            total: 24,
+        };
+        assert_eq!(coverage.to_string(), "ja (Main) — 23/24 files");
+    }
+
     #[test]
     fn test_clone() {
         let s1 = Same::new("it", "it-IT", Some("Italian".to_owned()));
@@ -0,0 +1,278 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::language::{self, LanguageTag};
+use crate::matroska::{Matroska, MatroskaTrack, MatroskaTrackType};
+use crate::plan::TrackEdit;
+
+/// Language code of the tracks without a language
+pub const UNDETERMINED: &str = "und";
+
+/// Language given to the undetermined tracks of a type, the name is matched case-insensitively as a substring
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LanguageRule {
+    pub type_: MatroskaTrackType,
+    pub name: Option<String>,
+    /// Language as an ISO 639-2 code or a BCP 47 tag (e.g. 'eng' or 'en-US')
+    pub language: String,
+}
+
+/// Undetermined tracks of the files sharing a type, a name and a codec
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UndGroup {
+    pub type_: MatroskaTrackType,
+    pub name: Option<String>,
+    pub codec: Option<String>,
+    /// Number of files having the group
+    pub count: usize,
+    pub total: usize,
+}
+
+impl LanguageRule {
+    /// Does the rule apply to the group
+    pub fn matches(&self, group: &UndGroup) -> bool {
+        let name = group.name.as_deref().unwrap_or_default().to_lowercase();
+        self.type_ == group.type_
+            && self
+                .name
+                .as_ref()
+                .is_none_or(|pattern| name.contains(&pattern.to_lowercase()))
+    }
+}
+
+/// Parse a rule of the command line: 'TYPE[:NAME]=LANGUAGE' (e.g. 'audio=eng' or 'subtitle:Signs=en')
+impl FromStr for LanguageRule {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let expected = || format!("expected 'TYPE[:NAME]=LANGUAGE', got '{input}'");
+        let (selector, language) = input.split_once('=').ok_or_else(expected)?;
+        let (type_, name) = match selector.split_once(':') {
+            Some((type_, name)) => (type_, Some(name.to_owned())),
+            None => (selector, None),
+        };
+        let type_ = match type_ {
+            "audio" => MatroskaTrackType::Audio,
+            "subtitle" => MatroskaTrackType::Subtitles,
+            "video" => MatroskaTrackType::Video,
+            _ => {
+                return Err(format!(
+                    "expected 'audio', 'subtitle' or 'video', got '{type_}'"
+                ))
+            }
+        };
+        validate_language(language)?;
+
+        Ok(Self {
+            type_,
+            name,
+            language: language.to_owned(),
+        })
+    }
+}
+
+impl UndGroup {
+    /// Is the track an undetermined track of the group
+    pub fn matches_track(&self, track: &MatroskaTrack) -> bool {
+        is_undetermined(track)
+            && track.type_ == self.type_
+            && track.name == self.name
+            && track.codec == self.codec
+    }
+}
+
+impl fmt::Display for UndGroup {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.type_)?;
+        if let Some(name) = &self.name {
+            write!(f, " ({name})")?;
+        }
+        if let Some(codec) = &self.codec {
+            write!(f, " [{codec}]")?;
+        }
+        write!(f, " — {}/{} files", self.count, self.total)
+    }
+}
+
+/// Check that the language can be written to a track, 'und' is refused
+/// and every subtag has 1 to 8 letters or digits
+pub fn validate_language(language: &str) -> Result<(), String> {
+    let subtags = language.split('-').all(|subtag| {
+        (1..=8).contains(&subtag.len()) && subtag.chars().all(|c| c.is_ascii_alphanumeric())
+    });
+    match language::legacy_code(language) {
+        Some(code) if subtags && code != UNDETERMINED => Ok(()),
+        _ => Err(format!(
+            "expected an ISO 639-2 code or a BCP 47 tag (e.g. 'eng' or 'en-US'), got '{language}'"
+        )),
+    }
+}
+
+/// Is the language of the track undetermined, in its language ietf and its language
+pub fn is_undetermined(track: &MatroskaTrack) -> bool {
+    LanguageTag::from_track(&track.language, &track.language_ietf).is_undetermined()
+}
+
+/// Get the groups of undetermined tracks among the files, in the order of the files
+pub fn get_und_groups(mkvs: &[Matroska]) -> Vec<UndGroup> {
+    let mut groups: Vec<UndGroup> = vec![];
+    for matroska in mkvs {
+        for track in matroska
+            .tracks
+            .iter()
+            .filter(|track| is_undetermined(track))
+        {
+            if groups.iter().any(|group| group.matches_track(track)) {
+                continue;
+            }
+            groups.push(UndGroup {
+                type_: track.type_.clone(),
+                name: track.name.clone(),
+                codec: track.codec.clone(),
+                count: 0,
+                total: mkvs.len(),
+            });
+        }
+    }
+
+    for group in &mut groups {
+        group.count = mkvs
+            .iter()
+            .filter(|matroska| {
+                matroska
+                    .tracks
+                    .iter()
+                    .any(|track| group.matches_track(track))
+            })
+            .count();
+    }
+    groups
+}
+
+/// Give their language to the undetermined tracks of the groups, so they can be chosen as default,
+/// and get the indexes of the tagged tracks of each file
+pub fn assign_languages(
+    mkvs: &mut [Matroska],
+    assignments: &[(UndGroup, String)],
+) -> Vec<Vec<usize>> {
+    mkvs.iter_mut()
+        .map(|matroska| {
+            let mut tagged = vec![];
+            for (index, track) in matroska.tracks.iter_mut().enumerate() {
+                let Some((_, language)) = assignments
+                    .iter()
+                    .find(|(group, _)| group.matches_track(track))
+                else {
+                    continue;
+                };
+                track.language = language::legacy_code(language).unwrap_or_default();
+                track.language_ietf = language::normalize(language);
+                tagged.push(index);
+            }
+            tagged
+        })
+        .collect()
+}
+
+/// Plan the languages of the tagged tracks
+pub fn plan_languages<'a>(tracks: Vec<&'a MatroskaTrack>) -> Vec<TrackEdit<'a>> {
+    tracks
+        .into_iter()
+        .map(|track| TrackEdit {
+            language: Some((track.language.clone(), track.language_ietf.clone())),
+            ..TrackEdit::untouched(track)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track(type_: MatroskaTrackType, name: Option<&str>, language: &str) -> MatroskaTrack {
+        MatroskaTrack {
+            id: 0,
+            number: Some(1),
+            codec: Some("AAC".to_string()),
+            name: name.map(|name| name.to_string()),
+            type_,
+            language: language.to_string(),
+            language_ietf: UNDETERMINED.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn matroska(path: &str, tracks: Vec<MatroskaTrack>) -> Matroska {
+        Matroska {
+            path: path.to_string(),
+            tracks,
+        }
+    }
+
+    #[test]
+    fn test_from_str() {
+        let rule: LanguageRule = "subtitle:Signs=en-US".parse().unwrap();
+        assert_eq!(rule.type_, MatroskaTrackType::Subtitles);
+        assert_eq!(rule.name.as_deref(), Some("Signs"));
+        assert_eq!(rule.language, "en-US");
+
+        assert!("audio=eng".parse::<LanguageRule>().is_ok());
+        assert!("audio".parse::<LanguageRule>().is_err());
+        assert!("buttons=eng".parse::<LanguageRule>().is_err());
+        assert!("audio=und".parse::<LanguageRule>().is_err());
+        assert!("audio=english".parse::<LanguageRule>().is_err());
+        assert!("audio=en-US".parse::<LanguageRule>().is_ok());
+        assert!("audio=eng-x y".parse::<LanguageRule>().is_err());
+        assert!("audio=en--US".parse::<LanguageRule>().is_err());
+        assert!("audio=en-abcdefghi".parse::<LanguageRule>().is_err());
+    }
+
+    #[test]
+    fn test_get_und_groups() {
+        let mkvs = [
+            matroska(
+                "a.mkv",
+                vec![
+                    track(MatroskaTrackType::Audio, None, "und"),
+                    track(MatroskaTrackType::Audio, None, "jpn"),
+                    track(MatroskaTrackType::Subtitles, Some("Signs"), "und"),
+                ],
+            ),
+            matroska("b.mkv", vec![track(MatroskaTrackType::Audio, None, "und")]),
+        ];
+
+        let groups = get_und_groups(&mkvs);
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].to_string(), "audio [AAC] — 2/2 files");
+        assert_eq!(groups[1].to_string(), "subtitle (Signs) [AAC] — 1/2 files");
+
+        let rule: LanguageRule = "subtitle:signs=eng".parse().unwrap();
+        assert!(!rule.matches(&groups[0]));
+        assert!(rule.matches(&groups[1]));
+    }
+
+    #[test]
+    fn test_assign_languages() {
+        let mut mkvs = [matroska(
+            "a.mkv",
+            vec![
+                track(MatroskaTrackType::Audio, None, "jpn"),
+                track(MatroskaTrackType::Audio, None, "und"),
+                track(MatroskaTrackType::Subtitles, None, "und"),
+            ],
+        )];
+        let groups = get_und_groups(&mkvs);
+
+        let tagged = assign_languages(&mut mkvs, &[(groups[0].clone(), "en-US".to_string())]);
+        assert_eq!(tagged, vec![vec![1]]);
+        assert_eq!(mkvs[0].tracks[1].language, "eng");
+        assert_eq!(mkvs[0].tracks[1].language_ietf, "en-US");
+        assert!(is_undetermined(&mkvs[0].tracks[2]));
+
+        let edits = plan_languages(vec![&mkvs[0].tracks[1]]);
+        assert_eq!(
+            edits[0].to_args().unwrap(),
+            " --edit track:1 --set language=eng --set language-ietf=en-US"
+        );
+    }
+}
@@ -0,0 +1,48 @@
+use serde::Deserialize;
+
+use crate::matroska::MatroskaTrack;
+
+/// Wanted resolution of the video tracks, unset fields are ignored
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct VideoQuality {
+    /// Prefer the tracks with the closest height (e.g. '1080')
+    pub height: Option<u64>,
+}
+
+impl VideoQuality {
+    /// Get the distance of the track to the wanted height, the lowest is the best and unknown heights come last
+    pub fn rank(&self, track: &MatroskaTrack) -> u64 {
+        match (self.height, track.video_height()) {
+            (None, _) => 0,
+            (Some(_), None) => u64::MAX,
+            (Some(wanted), Some(height)) => wanted.abs_diff(height),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matroska::MatroskaTrackType;
+
+    fn track(dimensions: Option<&str>) -> MatroskaTrack {
+        MatroskaTrack {
+            id: 0,
+            video_pixel_dimensions: dimensions.map(|dimensions| dimensions.to_string()),
+            type_: MatroskaTrackType::Video,
+            language: "und".to_string(),
+            language_ietf: "und".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_rank() {
+        let quality = VideoQuality { height: Some(1080) };
+        assert_eq!(quality.rank(&track(Some("1920x1080"))), 0);
+        assert!(quality.rank(&track(Some("720x480"))) < quality.rank(&track(Some("3840x2160"))));
+        assert_eq!(quality.rank(&track(None)), u64::MAX);
+        assert_eq!(VideoQuality::default().rank(&track(None)), 0);
+    }
+}